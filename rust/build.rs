@@ -0,0 +1,34 @@
+use chrono::{SecondsFormat, Utc};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Run a git command and return its trimmed output
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn main() {
+    // Prefer an explicit GIT_SHA (e.g. a Docker build arg), since the build may run outside a checkout
+    let git_sha = env::var("GIT_SHA").ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| git(&["rev-parse", "--short", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    
+    let build_timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    
+    println!("cargo:rustc-env=WATCHER_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=WATCHER_BUILD_TIMESTAMP={}", build_timestamp);
+    
+    // Rebuild the version info when the checked out commit changes
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = PathBuf::from(git_dir);
+        println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", git_dir.join("refs/heads").display());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+
+use crate::config::{GlobalSettings, ServiceConfig, ServiceType};
+use crate::docker_utils::{container_command, get_container_logs, resolve_container_name, ContainerStatus};
+use crate::service::{check_health, check_log_files, check_service_status, restart_service, run_validation, ServiceHandler};
+use crate::utils::fix_permissions;
+
+/// Config test commands tried in order; Debian-based images ship apachectl, the official httpd image only httpd
+const CONFIG_TEST_COMMANDS: &[&[&str]] = &[&["apachectl", "-t"], &["httpd", "-t"]];
+
+/// Exit code of `docker exec` when the command does not exist in the container
+const COMMAND_NOT_FOUND: i32 = 127;
+
+/// Severities of Apache error log lines (`[core:error]`, `[ssl:emerg]`, ...) reported while monitoring
+const ERROR_LEVELS: &[&str] = &[":error]", ":crit]", ":alert]", ":emerg]"];
+
+/// Service handler for Apache httpd containers
+pub struct ApacheService<'a> {
+    service: &'a ServiceConfig,
+    global: &'a GlobalSettings,
+}
+
+impl<'a> ApacheService<'a> {
+    /// Create a new ApacheService instance
+    pub fn new(service: &'a ServiceConfig, global: &'a GlobalSettings) -> Result<Self> {
+        if service.service_type != ServiceType::Apache {
+            return Err(anyhow!("Service type mismatch: expected Apache service"));
+        }
+        
+        Ok(Self { service, global })
+    }
+
+    /// Validate the Apache configuration with the validation command, or `apachectl -t` in the container
+    pub async fn validate_config(&self) -> Result<bool> {
+        info!(service = self.service.name; "Validating Apache configuration");
+        
+        if let Some(cmd) = self.service.effective_validation_command(self.global) {
+            return match run_validation(self.service, &cmd).await {
+                Ok(()) => Ok(true),
+                Err(e) => {
+                    warn!(service = self.service.name; "{}", e);
+                    Ok(false)
+                }
+            };
+        }
+        
+        let container_name = resolve_container_name(&self.service.container_name).await?;
+        
+        for test in CONFIG_TEST_COMMANDS {
+            let output = container_command()
+                .arg("exec")
+                .arg(&container_name)
+                .args(*test)
+                .output()
+                .await
+                .context(format!("Failed to execute {} in container {}", test.join(" "), container_name))?;
+            
+            if output.status.code() == Some(COMMAND_NOT_FOUND) {
+                debug!(service = self.service.name; "{} not found in the container", test[0]);
+                continue;
+            }
+            
+            if !output.status.success() {
+                warn!(service = self.service.name; "Apache configuration test failed: {}",
+                                                   String::from_utf8_lossy(&output.stderr).trim());
+                return Ok(false);
+            }
+            
+            info!(service = self.service.name; "Apache configuration test passed");
+            return Ok(true);
+        }
+        
+        Err(anyhow!("Neither apachectl nor httpd found in container {}; set a validation_command", container_name))
+    }
+
+    /// Scan the container output for Apache errors, which the official image writes to stderr
+    pub async fn monitor_logs(&self) -> Result<Vec<String>> {
+        let logs = get_container_logs(&self.service.container_name, self.service.log_tail_lines, None, self.service.log_streams).await?;
+        
+        Ok(logs.lines()
+            .filter(|(_, line)| ERROR_LEVELS.iter().any(|level| line.contains(level)))
+            .map(|(stream, line)| format!("{}: {}", stream, line))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl<'a> ServiceHandler for ApacheService<'a> {
+    fn name(&self) -> &str {
+        &self.service.name
+    }
+
+    fn service_type(&self) -> ServiceType {
+        ServiceType::Apache
+    }
+
+    async fn validate(&self) -> Result<bool> {
+        self.validate_config().await
+    }
+
+    async fn restart(&self) -> Result<()> {
+        restart_service(self.service, self.global).await
+    }
+
+    async fn fix_issues(&self) -> Result<()> {
+        // Nothing is fixed automatically; a broken config fails validation instead
+        Ok(())
+    }
+
+    async fn fix_permissions(&self) -> Result<()> {
+        if !self.service.effective_fix_permissions(self.global.fix_permissions) {
+            return Ok(());
+        }
+        
+        match &self.service.permissions {
+            Some(permissions) => fix_permissions(self.service, permissions, self.global).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn monitor(&self) -> Result<Vec<String>> {
+        match check_service_status(self.service).await? {
+            ContainerStatus::Running => {
+                let mut problems = self.monitor_logs().await?;
+                problems.extend(check_log_files(self.service, self.global).await?);
+                Ok(problems)
+            }
+            status => Ok(vec![format!("Service is not running: {:?}", status)]),
+        }
+    }
+
+    async fn health(&self) -> Result<bool> {
+        check_health(self.service, self.global).await
+    }
+}
@@ -1,10 +1,15 @@
 use anyhow::{anyhow, Context, Result};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use regex::Regex;
+
+use crate::utils::parse_duration;
 
 /// Service type enumeration for specialized handling
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -13,11 +18,172 @@ pub enum ServiceType {
     Nginx,
     Apache,
     Generic,
+    Kubernetes,
     Custom(String),
 }
 
+/// Output format for log lines
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Container engine whose CLI runs container commands
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// Binary of the runtime's CLI
+    pub fn binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+
+    /// Standalone compose binary used when the CLI has no working `compose` subcommand
+    pub fn compose_binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker-compose",
+            ContainerRuntime::Podman => "podman-compose",
+        }
+    }
+}
+
+/// What to do when `local_path` exists with content but is not a git repository
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NonEmptyDirPolicy {
+    /// Move the directory aside to `<local_path>.bak` and clone fresh
+    #[default]
+    Backup,
+    /// Refuse to touch the directory
+    Fail,
+    /// Initialize git in the directory and fetch, keeping the existing files
+    InitInPlace,
+}
+
+/// How a pull that hits a merge conflict with local commits is resolved
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Reset to the commit before the pull and fail the update
+    #[default]
+    Abort,
+    /// Reset to the remote branch, discarding local commits
+    Theirs,
+    /// Merge again, taking the local side of each conflicting hunk
+    Ours,
+}
+
+/// Container output streams scanned for errors
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStreams {
+    #[default]
+    Both,
+    Stdout,
+    Stderr,
+}
+
+impl LogStreams {
+    /// Whether stdout is scanned
+    pub fn stdout(self) -> bool {
+        self != LogStreams::Stderr
+    }
+    
+    /// Whether stderr is scanned
+    pub fn stderr(self) -> bool {
+        self != LogStreams::Stdout
+    }
+}
+
+/// What happens to uncommitted changes in the checkout when an update is pulled
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalChangesPolicy {
+    /// Stash them before the pull and pop them afterwards
+    #[default]
+    Stash,
+    /// Hard reset to the remote branch, dropping local changes and commits
+    Discard,
+    /// Rebase local commits onto the remote branch, carrying uncommitted changes along
+    Rebase,
+}
+
+/// Git hosting service whose push webhooks the webhook server accepts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookProvider {
+    Github,
+    Gitlab,
+    Bitbucket,
+    Gitea,
+}
+
+/// Commands implementing a user-defined `ServiceType::Custom` type
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CustomHandler {
+    #[serde(default)]
+    pub validate_command: Option<String>,
+    #[serde(default)]
+    pub restart_command: Option<String>,
+    #[serde(default)]
+    pub monitor_command: Option<String>,
+    #[serde(default)]
+    pub health_command: Option<String>,
+}
+
+/// A log file inside the container, tailed while monitoring the service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogFile {
+    pub path: String,
+    /// Regexes marking a line as an error; lines mentioning "error" in any case if empty
+    #[serde(default)]
+    pub error_patterns: Vec<String>,
+}
+
+impl LogFile {
+    /// Compile the error patterns
+    pub fn error_regexes(&self) -> Result<Vec<Regex>> {
+        if self.error_patterns.is_empty() {
+            return Ok(vec![Regex::new("(?i)error").expect("default pattern is valid")]);
+        }
+        
+        self.error_patterns.iter()
+            .map(|pattern| Regex::new(pattern)
+                .context(format!("Invalid error pattern for log file {}: {}", self.path, pattern)))
+            .collect()
+    }
+}
+
+/// Additional repository checked out for a service, such as shared snippets next to its own config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtraRepo {
+    pub repo_url: String,
+    /// Defaults to the service's branch
+    #[serde(default)]
+    pub branch: Option<String>,
+    pub local_path: PathBuf,
+}
+
+impl ExtraRepo {
+    /// Get the branch to follow, falling back to the service's branch
+    pub fn effective_branch(&self, service: &ServiceConfig, default_branch: &str) -> String {
+        self.branch.clone().unwrap_or_else(|| service.effective_branch(default_branch))
+    }
+}
+
 /// Permissions configuration for file ownership
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Permissions {
     pub fix: bool,
     pub user: String,
@@ -25,7 +191,7 @@ pub struct Permissions {
 }
 
 /// Individual service configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ServiceConfig {
     // Service identification
     pub name: String,
@@ -35,26 +201,131 @@ pub struct ServiceConfig {
     
     // Repository settings
     pub repo_url: String,
+    #[serde(default)]
+    pub mirror_urls: Vec<String>,
     pub branch: Option<String>,
     pub local_path: PathBuf,
+    /// More repositories feeding the same service; an update in any of them triggers one validate/restart
+    #[serde(default)]
+    pub extra_repos: Vec<ExtraRepo>,
+    /// Glob patterns (e.g. `hotfix/*`) of remote branches to report when their tip moves, without deploying them
+    #[serde(default)]
+    pub notify_branches: Vec<String>,
+    /// Commit or tag (e.g. `v1.4.2`) the checkout is held at instead of following the branch
+    #[serde(default, alias = "pin")]
+    pub pinned_commit: Option<String>,
+    #[serde(default = "default_true")]
+    pub watch: bool,
+    #[serde(default)]
+    pub watch_paths: Vec<String>,
+    #[serde(default)]
+    pub sparse_path: Option<String>,
+    #[serde(default)]
+    pub clone_into_nonempty: NonEmptyDirPolicy,
+    #[serde(default)]
+    pub conflict_strategy: ConflictStrategy,
+    #[serde(default)]
+    pub local_changes_policy: LocalChangesPolicy,
+    /// Check the branch out as a worktree of a bare clone shared with other services on the same repo
+    #[serde(default)]
+    pub shared_clone: bool,
     
     // Container settings
     #[serde(default)]
     pub use_docker_compose: bool,
-    pub docker_compose_file: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub docker_compose_file: Vec<String>,
     #[serde(default)]
     pub docker_compose_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub compose_profiles: Vec<String>,
+    /// Env file passed to compose with `--env-file`, relative to the compose directory
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
     pub restart_command: Option<String>,
     pub validation_command: Option<String>,
+    /// Command retried after a restart until it exits 0, e.g. `pg_isready`
+    pub readiness_command: Option<String>,
+    /// Command telling whether the service is healthy right now by its exit code, e.g. `systemctl is-active nginx`
+    #[serde(default)]
+    pub health_command: Option<String>,
+    /// URL whose success status tells that the service is healthy, checked when there is no health command
+    #[serde(default)]
+    pub health_url: Option<String>,
+    /// Times `health_url` is requested after a restart before the update is reverted (0 skips the check)
+    #[serde(default = "default_health_check_attempts")]
+    pub health_check_attempts: u32,
+    /// Delay between post-restart requests to `health_url`
+    #[serde(default = "default_health_check_delay")]
+    pub health_check_delay: String,
+    /// Times the service status is checked after a restart before the update fails (0 skips the check)
+    #[serde(default)]
+    pub readiness_attempts: u32,
+    /// Delay between readiness status checks
+    #[serde(default = "default_readiness_delay")]
+    pub readiness_delay: String,
+    /// Docker networks the container must be attached to after a restart (full names, e.g. `myproject_backend`)
+    #[serde(default)]
+    pub expected_networks: Vec<String>,
+    #[serde(default)]
+    pub validation_in_container: bool,
+    /// Never start a stopped container while the checked-out commit is one that failed validation
+    #[serde(default)]
+    pub keep_stopped_on_invalid: bool,
+    /// Only report when the remote is ahead of the checkout; never pull, validate or restart
+    #[serde(default)]
+    pub read_only: bool,
+    /// Validate the remote tip in a temporary worktree and only pull it into `local_path` if validation passes
+    #[serde(default)]
+    pub stage_before_apply: bool,
+    /// Tag the commit locally as `deployed/<service>/<timestamp>` after a successful update
+    #[serde(default)]
+    pub tag_deploys: bool,
+    /// Also push deploy tags to the remote; a rejected push is only logged
+    #[serde(default)]
+    pub push_deploy_tags: bool,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub deployment: Option<String>,
+    #[serde(default)]
+    pub pre_apply_command: Option<String>,
+    #[serde(default)]
+    pub post_apply_command: Option<String>,
+    #[serde(default)]
+    pub command_cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub command_env: HashMap<String, String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
     
     // Behavior settings
     #[serde(default)]
     pub disable_restart: bool,
     pub healthcheck_url: Option<String>,
     pub auto_fix: Option<bool>,
+    #[serde(default)]
+    pub revert_on_failure: Option<bool>,
     pub monitor_logs: Option<bool>,
     #[serde(default = "default_log_tail_lines")]
     pub log_tail_lines: u32,
+    #[serde(default)]
+    pub log_error_examples: Option<usize>,
+    /// Which container output streams log monitoring scans, e.g. only stderr when stdout carries structured logs
+    #[serde(default)]
+    pub log_streams: LogStreams,
+    /// Log files inside the container to scan for errors during monitoring, for any service type
+    #[serde(default)]
+    pub log_files: Vec<LogFile>,
+    /// Check container CPU and memory usage when no update was applied
+    #[serde(default)]
+    pub monitor_resources: bool,
+    #[serde(default = "default_max_cpu_percent")]
+    pub max_cpu_percent: f64,
+    #[serde(default = "default_max_memory_percent")]
+    pub max_memory_percent: f64,
+    #[serde(default)]
+    pub startup_grace_period: Option<String>,
     
     // Permissions
     pub permissions: Option<Permissions>,
@@ -65,7 +336,7 @@ pub struct ServiceConfig {
 }
 
 /// Global settings for application behavior
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GlobalSettings {
     #[serde(default = "default_watch_interval")]
     pub watch_interval: u64,
@@ -73,13 +344,23 @@ pub struct GlobalSettings {
     pub default_branch: String,
     #[serde(default)]
     pub auto_fix: bool,
+    #[serde(default)]
+    pub revert_on_failure: Option<bool>,
     #[serde(default = "default_true")]
     pub fix_permissions: bool,
+    #[serde(default)]
+    pub allow_unsafe_permission_paths: bool,
+    #[serde(default = "default_min_permission_path_depth")]
+    pub min_permission_path_depth: usize,
     #[serde(default = "default_true")]
     pub monitor_logs: bool,
+    #[serde(default = "default_log_error_examples")]
+    pub log_error_examples: usize,
     #[serde(default)]
     pub disable_restart: bool,
     #[serde(default)]
+    pub container_runtime: ContainerRuntime,
+    #[serde(default)]
     pub use_docker_compose: bool,
     #[serde(default)]
     pub default_compose_dir: Option<PathBuf>,
@@ -87,6 +368,76 @@ pub struct GlobalSettings {
     pub default_compose_file: Option<String>,
     #[serde(default = "default_startup_grace_period")]
     pub startup_grace_period: String,
+    #[serde(default)]
+    pub skip_startup_grace: bool,
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: String,
+    #[serde(default = "default_git_timeout")]
+    pub git_timeout: String,
+    /// Longest to wait for a container to be running (and healthy) after a compose restart
+    #[serde(default = "default_container_ready_timeout")]
+    pub container_ready_timeout: String,
+    /// Longest to wait for a container caught restarting to settle before acting on it
+    #[serde(default = "default_restarting_grace_period")]
+    pub restarting_grace_period: String,
+    /// User (name or uid) git, validation and custom commands run as; ownership fixes still run as the watcher
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// Group (name or gid) for `run_as_user`, defaulting to the user's primary group
+    #[serde(default)]
+    pub run_as_group: Option<String>,
+    /// Directory holding the bare clones of services with `shared_clone`
+    #[serde(default = "default_shared_clone_dir")]
+    pub shared_clone_dir: PathBuf,
+    #[serde(default = "default_notification_window")]
+    pub notification_window: String,
+    /// Attempts made to deliver a healthcheck ping, with backoff between them, before warning
+    #[serde(default = "default_healthcheck_attempts")]
+    pub healthcheck_attempts: u32,
+    /// Longest a single healthcheck ping attempt may take
+    #[serde(default = "default_healthcheck_timeout")]
+    pub healthcheck_timeout: String,
+    /// Longest a single check/validate/restart cycle may take before it is aborted
+    #[serde(default)]
+    pub cycle_timeout: Option<String>,
+    /// Consecutive failed checks of any service after which the watcher exits non-zero (0 disables)
+    #[serde(default)]
+    pub max_failures_before_exit: u32,
+    /// Consecutive validation failures after which a service stops pulling updates for a cooldown (0 disables)
+    #[serde(default)]
+    pub circuit_breaker_threshold: u32,
+    #[serde(default = "default_circuit_breaker_cooldown")]
+    pub circuit_breaker_cooldown: String,
+    /// Longest a service waits between checks while they keep failing, backing off from the watch interval
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: String,
+    /// Longest to wait at shutdown for in-flight cycles to finish before aborting them
+    /// (the container's stop timeout must be longer for this to help)
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: String,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub verbose: bool,
+    #[serde(default)]
+    pub log_file: Option<String>,
+    #[serde(default)]
+    pub custom_handlers: HashMap<String, CustomHandler>,
+    #[serde(default)]
+    pub webhook_port: Option<u16>,
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    #[serde(default)]
+    pub webhook_provider: Option<WebhookProvider>,
+    #[serde(default)]
+    pub ssh_key_file: Option<PathBuf>,
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+    /// JSON file the state of every service is written to after each cycle (null disables)
+    #[serde(default = "default_status_file")]
+    pub status_file: Option<PathBuf>,
 }
 
 /// Main configuration containing all services and global settings
@@ -95,9 +446,50 @@ pub struct Config {
     pub services: Vec<ServiceConfig>,
     #[serde(default)]
     pub global_settings: GlobalSettings,
+    /// File the configuration was loaded from, if any
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// Environment variables read by the legacy config, with their services file equivalents
+const LEGACY_ENV_VARS: &[(&str, &str)] = &[
+    ("REPO_URL", "services[].repo_url"),
+    ("BRANCH", "services[].branch"),
+    ("WATCH_INTERVAL", "global_settings.watch_interval"),
+    ("NGINX_CONTAINER_NAME", "services[].container_name"),
+    ("CONFIG_DIR", "services[].local_path"),
+    ("LOCKFILE", "nothing; the lockfile path is fixed"),
+    ("USE_DOCKER_COMPOSE", "services[].use_docker_compose"),
+    ("COMPOSE_FILE", "services[].docker_compose_file"),
+    ("COMPOSE_DIR", "services[].docker_compose_dir"),
+    ("VERBOSE", "global_settings.verbose"),
+    ("LOG_FILE", "global_settings.log_file"),
+    ("DISABLE_RESTART", "services[].disable_restart"),
+    ("HEALTHCHECK_URL", "services[].healthcheck_url"),
+    ("AUTO_FIX", "services[].auto_fix"),
+    ("MONITOR_LOGS", "services[].monitor_logs"),
+    ("LOG_TAIL_LINES", "services[].log_tail_lines"),
+    ("FIX_PERMISSIONS", "services[].permissions.fix"),
+    ("NGINX_USER", "services[].permissions.user"),
+    ("NGINX_GROUP", "services[].permissions.group"),
+    ("WEB_ROOT", "services[].custom_settings.web_root"),
+    ("ENABLE_DIR_LISTING", "services[].custom_settings.enable_dir_listing"),
+    ("SSH_PRIVATE_KEY", "global_settings.ssh_key_file"),
+];
+
+/// A channel events are sent to besides the log and the services' healthcheck URLs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    /// Slack incoming webhook
+    Slack { webhook_url: String },
+    /// Any URL accepting the events as JSON
+    Webhook { url: String },
 }
 
 /// Legacy configuration for backward compatibility
+///
+/// Deprecated: configure services with a services file instead of environment variables.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LegacyConfig {
     // Repository settings
@@ -119,6 +511,7 @@ pub struct LegacyConfig {
     
     // Behavior settings
     pub verbose: bool,
+    pub log_file: Option<String>,
     pub disable_restart: bool,
     pub healthcheck_url: Option<String>,
     pub auto_fix: bool,
@@ -132,6 +525,34 @@ pub struct LegacyConfig {
     pub nginx_group: String,
 }
 
+/// Config file extensions recognized when searching the default locations
+const CONFIG_EXTENSIONS: &[&str] = &["json", "toml", "yaml", "yml"];
+
+/// Config path that reads the config from stdin instead of a file
+pub const STDIN_CONFIG: &str = "-";
+
+/// Format of a services config, given explicitly when there is no file extension (stdin)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl std::str::FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            other => Err(anyhow!("Unsupported config format '{}' (expected json, toml or yaml)", other)),
+        }
+    }
+}
+
 // Default function implementations
 fn default_service_type() -> ServiceType {
     ServiceType::Generic
@@ -141,6 +562,14 @@ fn default_watch_interval() -> u64 {
     60
 }
 
+fn default_max_cpu_percent() -> f64 {
+    90.0
+}
+
+fn default_max_memory_percent() -> f64 {
+    90.0
+}
+
 fn default_log_tail_lines() -> u32 {
     100
 }
@@ -149,10 +578,101 @@ fn default_true() -> bool {
     true
 }
 
+fn default_log_error_examples() -> usize {
+    5
+}
+
 fn default_startup_grace_period() -> String {
     "30s".to_string()
 }
 
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_delay() -> String {
+    "5s".to_string()
+}
+
+fn default_git_timeout() -> String {
+    "120s".to_string()
+}
+
+fn default_container_ready_timeout() -> String {
+    "60s".to_string()
+}
+
+fn default_restarting_grace_period() -> String {
+    "30s".to_string()
+}
+
+fn default_shared_clone_dir() -> PathBuf {
+    PathBuf::from("/var/lib/watcher/repos")
+}
+
+fn default_readiness_delay() -> String {
+    "2s".to_string()
+}
+
+fn default_health_check_attempts() -> u32 {
+    10
+}
+
+fn default_health_check_delay() -> String {
+    "3s".to_string()
+}
+
+fn default_status_file() -> Option<PathBuf> {
+    Some(PathBuf::from("/var/run/watcher-status.json"))
+}
+
+fn default_notification_window() -> String {
+    "5m".to_string()
+}
+
+fn default_healthcheck_attempts() -> u32 {
+    crate::logger::DEFAULT_HEALTHCHECK_ATTEMPTS
+}
+
+fn default_healthcheck_timeout() -> String {
+    "10s".to_string()
+}
+
+fn default_circuit_breaker_cooldown() -> String {
+    "30m".to_string()
+}
+
+fn default_max_backoff() -> String {
+    "30m".to_string()
+}
+
+fn default_shutdown_timeout() -> String {
+    "60s".to_string()
+}
+
+fn default_min_permission_path_depth() -> usize {
+    2
+}
+
+/// Accept either a single string or a list of strings
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(value)) => vec![value],
+        Some(OneOrMany::Many(values)) => values,
+        None => Vec::new(),
+    })
+}
+
 // Implementation blocks for the structs
 
 impl Default for GlobalSettings {
@@ -161,22 +681,121 @@ impl Default for GlobalSettings {
             watch_interval: default_watch_interval(),
             default_branch: "main".to_string(),
             auto_fix: false,
+            revert_on_failure: None,
             fix_permissions: default_true(),
+            allow_unsafe_permission_paths: false,
+            min_permission_path_depth: default_min_permission_path_depth(),
             monitor_logs: default_true(),
+            log_error_examples: default_log_error_examples(),
             disable_restart: false,
+            container_runtime: ContainerRuntime::default(),
             use_docker_compose: false,
             default_compose_dir: Some(PathBuf::from("/app/config")),
             default_compose_file: Some("docker-compose.yml".to_string()),
             startup_grace_period: default_startup_grace_period(),
+            skip_startup_grace: false,
+            retry_attempts: default_retry_attempts(),
+            retry_delay: default_retry_delay(),
+            git_timeout: default_git_timeout(),
+            container_ready_timeout: default_container_ready_timeout(),
+            restarting_grace_period: default_restarting_grace_period(),
+            run_as_user: None,
+            run_as_group: None,
+            shared_clone_dir: default_shared_clone_dir(),
+            notification_window: default_notification_window(),
+            healthcheck_attempts: default_healthcheck_attempts(),
+            healthcheck_timeout: default_healthcheck_timeout(),
+            cycle_timeout: None,
+            max_failures_before_exit: 0,
+            circuit_breaker_threshold: 0,
+            circuit_breaker_cooldown: default_circuit_breaker_cooldown(),
+            max_backoff: default_max_backoff(),
+            shutdown_timeout: default_shutdown_timeout(),
+            log_format: LogFormat::default(),
+            verbose: false,
+            log_file: None,
+            custom_handlers: HashMap::new(),
+            webhook_port: None,
+            webhook_secret: None,
+            webhook_provider: None,
+            ssh_key_file: None,
+            notifiers: Vec::new(),
+            status_file: default_status_file(),
         }
     }
 }
 
+impl GlobalSettings {
+    /// Get the user-defined handler for a custom service type, if one is registered
+    pub fn custom_handler(&self, service_type: &ServiceType) -> Option<&CustomHandler> {
+        match service_type {
+            ServiceType::Custom(name) => self.custom_handlers.get(name),
+            _ => None,
+        }
+    }
+    
+    /// Get the base delay between retries, falling back to the default if invalid
+    pub fn retry_delay_duration(&self) -> Duration {
+        parse_duration(&self.retry_delay).unwrap_or_else(|_| Duration::from_secs(5))
+    }
+    
+    /// Get the window within which repeated notifications of the same kind are suppressed
+    pub fn notification_window_duration(&self) -> Duration {
+        parse_duration(&self.notification_window).unwrap_or_else(|_| Duration::from_secs(300))
+    }
+    
+    /// Get the timeout of a single healthcheck ping attempt, falling back to the default if invalid
+    pub fn healthcheck_timeout_duration(&self) -> Duration {
+        parse_duration(&self.healthcheck_timeout).unwrap_or_else(|_| Duration::from_secs(10))
+    }
+    
+    /// Get how long a service with an open circuit breaker waits before probing for updates again
+    pub fn circuit_breaker_cooldown_duration(&self) -> Duration {
+        parse_duration(&self.circuit_breaker_cooldown).unwrap_or_else(|_| Duration::from_secs(1800))
+    }
+    
+    /// Get how long shutdown waits for in-flight cycles, falling back to the default if invalid
+    pub fn shutdown_timeout_duration(&self) -> Duration {
+        parse_duration(&self.shutdown_timeout).unwrap_or_else(|_| Duration::from_secs(60))
+    }
+    
+    /// Get the cap on the delay between failing checks, falling back to the default if invalid
+    pub fn max_backoff_duration(&self) -> Duration {
+        parse_duration(&self.max_backoff).unwrap_or_else(|_| Duration::from_secs(1800))
+    }
+    
+    /// Get the deadline of a monitor cycle, by default five watch intervals but at least ten minutes
+    /// so the git and dependency timeouts inside a cycle expire first
+    pub fn cycle_timeout_duration(&self) -> Duration {
+        let default = Duration::from_secs(self.watch_interval.saturating_mul(5)).max(Duration::from_secs(600));
+        match &self.cycle_timeout {
+            Some(timeout) => parse_duration(timeout).unwrap_or(default),
+            None => default,
+        }
+    }
+    
+    /// Get the longest a single git command may run, falling back to the default if invalid
+    pub fn git_timeout_duration(&self) -> Duration {
+        parse_duration(&self.git_timeout).unwrap_or_else(|_| Duration::from_secs(120))
+    }
+    
+    /// Get the longest to wait for a restarted container to come up, falling back to the default if invalid
+    pub fn container_ready_timeout_duration(&self) -> Duration {
+        parse_duration(&self.container_ready_timeout).unwrap_or_else(|_| Duration::from_secs(60))
+    }
+    
+    /// Get the longest to wait for a restarting container to settle, falling back to the default if invalid
+    pub fn restarting_grace_period_duration(&self) -> Duration {
+        parse_duration(&self.restarting_grace_period).unwrap_or_else(|_| Duration::from_secs(30))
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             global_settings: GlobalSettings::default(),
             services: vec![ServiceConfig::default_nginx()],
+            source: None,
         }
     }
 }
@@ -194,18 +813,60 @@ impl ServiceConfig {
             repo_url: "https://github.com/nuniesmith/nginx.git".to_string(),
             branch: Some("main".to_string()),
             local_path: config_dir.clone(),
+            pinned_commit: None,
+            watch: true,
+            watch_paths: Vec::new(),
+            sparse_path: None,
+            clone_into_nonempty: NonEmptyDirPolicy::default(),
+            log_streams: LogStreams::default(),
+            conflict_strategy: ConflictStrategy::default(),
+            local_changes_policy: LocalChangesPolicy::default(),
+            shared_clone: false,
+            mirror_urls: Vec::new(),
+            extra_repos: Vec::new(),
+            notify_branches: Vec::new(),
             
             use_docker_compose: false,
-            docker_compose_file: None,
+            docker_compose_file: Vec::new(),
             docker_compose_dir: None,
+            compose_profiles: Vec::new(),
+            env_file: None,
             restart_command: Some("docker restart nginx_app".to_string()),
             validation_command: Some("docker exec -t nginx_app nginx -t".to_string()),
+            readiness_command: None,
+            health_command: None,
+            health_url: None,
+            health_check_attempts: default_health_check_attempts(),
+            health_check_delay: default_health_check_delay(),
+            readiness_attempts: 0,
+            readiness_delay: default_readiness_delay(),
+            expected_networks: Vec::new(),
+            validation_in_container: false,
+            keep_stopped_on_invalid: false,
+            read_only: false,
+            stage_before_apply: false,
+            tag_deploys: false,
+            push_deploy_tags: false,
+            namespace: None,
+            deployment: None,
+            pre_apply_command: None,
+            post_apply_command: None,
+            command_cwd: None,
+            command_env: HashMap::new(),
+            depends_on: Vec::new(),
             
             disable_restart: false,
             healthcheck_url: None,
             auto_fix: None,
+            revert_on_failure: None,
             monitor_logs: Some(true),
             log_tail_lines: default_log_tail_lines(),
+            log_error_examples: None,
+            log_files: Vec::new(),
+            monitor_resources: false,
+            max_cpu_percent: default_max_cpu_percent(),
+            max_memory_percent: default_max_memory_percent(),
+            startup_grace_period: None,
             
             permissions: Some(Permissions {
                 fix: true,
@@ -227,24 +888,102 @@ impl ServiceConfig {
         self.auto_fix.unwrap_or(default)
     }
     
+    /// Whether a failed validation reverts the checkout; follows auto_fix unless set explicitly
+    pub fn effective_revert_on_failure(&self, global: &GlobalSettings) -> bool {
+        self.revert_on_failure
+            .or(global.revert_on_failure)
+            .unwrap_or_else(|| self.effective_auto_fix(global.auto_fix))
+    }
+    
     /// Get the effective monitor_logs (considers the default)
     pub fn effective_monitor_logs(&self, default: bool) -> bool {
         self.monitor_logs.unwrap_or(default)
     }
     
+    /// Get the number of log error examples to show, falling back to the global setting if not set
+    pub fn effective_log_error_examples(&self, global: &GlobalSettings) -> usize {
+        self.log_error_examples.unwrap_or(global.log_error_examples)
+    }
+    
     /// Get the effective fix_permissions (considers the default)
     pub fn effective_fix_permissions(&self, default: bool) -> bool {
         self.permissions.as_ref().map_or(default, |p| p.fix)
     }
     
+    /// Get the validation command, falling back to the custom handler's command
+    pub fn effective_validation_command(&self, global: &GlobalSettings) -> Option<String> {
+        self.validation_command.clone().or_else(|| {
+            global.custom_handler(&self.service_type)
+                .and_then(|handler| handler.validate_command.clone())
+        })
+    }
+    
+    /// Get the restart command, falling back to the custom handler's command
+    pub fn effective_restart_command(&self, global: &GlobalSettings) -> Option<String> {
+        self.restart_command.clone().or_else(|| {
+            global.custom_handler(&self.service_type)
+                .and_then(|handler| handler.restart_command.clone())
+        })
+    }
+    
+    /// Get the health command, falling back to the custom handler's command
+    pub fn effective_health_command(&self, global: &GlobalSettings) -> Option<String> {
+        self.health_command.clone().or_else(|| {
+            global.custom_handler(&self.service_type)
+                .and_then(|handler| handler.health_command.clone())
+        })
+    }
+    
+    /// Get the delay between readiness status checks, falling back to the default if invalid
+    pub fn readiness_delay_duration(&self) -> Duration {
+        parse_duration(&self.readiness_delay).unwrap_or_else(|_| Duration::from_secs(2))
+    }
+    
+    /// Get the delay between post-restart health URL requests, falling back to the default if invalid
+    pub fn health_check_delay_duration(&self) -> Duration {
+        parse_duration(&self.health_check_delay).unwrap_or_else(|_| Duration::from_secs(3))
+    }
+    
+    /// Get the Kubernetes namespace, falling back to "default" if not set
+    pub fn effective_namespace(&self) -> String {
+        self.namespace.clone().unwrap_or_else(|| "default".to_string())
+    }
+    
+    /// Get the Kubernetes deployment name, falling back to the container name if not set
+    pub fn effective_deployment(&self) -> String {
+        self.deployment.clone().unwrap_or_else(|| self.container_name.clone())
+    }
+    
+    /// Get the directory holding the service's files: the sparse subtree if one is set
+    pub fn config_dir(&self) -> PathBuf {
+        match &self.sparse_path {
+            Some(sparse) => self.local_path.join(sparse),
+            None => self.local_path.clone(),
+        }
+    }
+    
+    /// Get the startup grace period, falling back to the global setting if not set (zero when skipped)
+    pub fn effective_startup_grace_period(&self, global: &GlobalSettings) -> Duration {
+        if global.skip_startup_grace {
+            return Duration::ZERO;
+        }
+        
+        let period = self.startup_grace_period.as_ref().unwrap_or(&global.startup_grace_period);
+        parse_duration(period).unwrap_or_else(|_| Duration::from_secs(30))
+    }
+    
     /// Get docker compose directory, falling back to the default if not set
     pub fn get_compose_dir(&self, default_dir: &Option<PathBuf>) -> Option<PathBuf> {
         self.docker_compose_dir.clone().or_else(|| default_dir.clone())
     }
     
-    /// Get docker compose file, falling back to the default if not set
-    pub fn get_compose_file(&self, default_file: &Option<String>) -> Option<String> {
-        self.docker_compose_file.clone().or_else(|| default_file.clone())
+    /// Get docker compose files in override order, falling back to the default if not set
+    pub fn get_compose_files(&self, default_file: &Option<String>) -> Vec<String> {
+        if self.docker_compose_file.is_empty() {
+            default_file.iter().cloned().collect()
+        } else {
+            self.docker_compose_file.clone()
+        }
     }
 }
 
@@ -268,6 +1007,7 @@ impl Default for LegacyConfig {
             web_root: "/var/www/html".to_string(),
             
             verbose: false,
+            log_file: None,
             disable_restart: false,
             healthcheck_url: None,
             auto_fix: false,
@@ -293,18 +1033,60 @@ impl From<&LegacyConfig> for Config {
             repo_url: legacy.repo_url.clone(),
             branch: Some(legacy.branch.clone()),
             local_path: legacy.config_dir.clone(),
+            pinned_commit: None,
+            watch: true,
+            watch_paths: Vec::new(),
+            sparse_path: None,
+            clone_into_nonempty: NonEmptyDirPolicy::default(),
+            log_streams: LogStreams::default(),
+            conflict_strategy: ConflictStrategy::default(),
+            local_changes_policy: LocalChangesPolicy::default(),
+            shared_clone: false,
+            mirror_urls: Vec::new(),
+            extra_repos: Vec::new(),
+            notify_branches: Vec::new(),
             
             use_docker_compose: legacy.use_docker_compose,
-            docker_compose_file: Some(legacy.compose_file.clone()),
+            docker_compose_file: vec![legacy.compose_file.clone()],
             docker_compose_dir: Some(legacy.compose_dir.clone()),
+            compose_profiles: Vec::new(),
+            env_file: None,
             restart_command: Some(format!("docker restart {}", legacy.nginx_container_name)),
             validation_command: Some(format!("docker exec -t {} nginx -t", legacy.nginx_container_name)),
+            readiness_command: None,
+            health_command: None,
+            health_url: None,
+            health_check_attempts: default_health_check_attempts(),
+            health_check_delay: default_health_check_delay(),
+            readiness_attempts: 0,
+            readiness_delay: default_readiness_delay(),
+            expected_networks: Vec::new(),
+            validation_in_container: false,
+            keep_stopped_on_invalid: false,
+            read_only: false,
+            stage_before_apply: false,
+            tag_deploys: false,
+            push_deploy_tags: false,
+            namespace: None,
+            deployment: None,
+            pre_apply_command: None,
+            post_apply_command: None,
+            command_cwd: None,
+            command_env: HashMap::new(),
+            depends_on: Vec::new(),
             
             disable_restart: legacy.disable_restart,
             healthcheck_url: legacy.healthcheck_url.clone(),
             auto_fix: Some(legacy.auto_fix),
+            revert_on_failure: None,
             monitor_logs: Some(legacy.monitor_logs),
             log_tail_lines: legacy.log_tail_lines,
+            log_error_examples: None,
+            log_files: Vec::new(),
+            monitor_resources: false,
+            max_cpu_percent: default_max_cpu_percent(),
+            max_memory_percent: default_max_memory_percent(),
+            startup_grace_period: None,
             
             permissions: Some(Permissions {
                 fix: legacy.fix_permissions,
@@ -324,38 +1106,119 @@ impl From<&LegacyConfig> for Config {
             watch_interval: legacy.watch_interval,
             default_branch: legacy.branch.clone(),
             auto_fix: legacy.auto_fix,
+            revert_on_failure: None,
             fix_permissions: legacy.fix_permissions,
+            allow_unsafe_permission_paths: false,
+            min_permission_path_depth: default_min_permission_path_depth(),
             monitor_logs: legacy.monitor_logs,
+            log_error_examples: default_log_error_examples(),
             disable_restart: legacy.disable_restart,
+            container_runtime: ContainerRuntime::default(),
             use_docker_compose: legacy.use_docker_compose,
             default_compose_dir: Some(legacy.compose_dir.clone()),
             default_compose_file: Some(legacy.compose_file.clone()),
             startup_grace_period: "30s".to_string(),
+            skip_startup_grace: false,
+            retry_attempts: default_retry_attempts(),
+            retry_delay: default_retry_delay(),
+            git_timeout: default_git_timeout(),
+            container_ready_timeout: default_container_ready_timeout(),
+            restarting_grace_period: default_restarting_grace_period(),
+            run_as_user: None,
+            run_as_group: None,
+            shared_clone_dir: default_shared_clone_dir(),
+            notification_window: default_notification_window(),
+            healthcheck_attempts: default_healthcheck_attempts(),
+            healthcheck_timeout: default_healthcheck_timeout(),
+            cycle_timeout: None,
+            max_failures_before_exit: 0,
+            circuit_breaker_threshold: 0,
+            circuit_breaker_cooldown: default_circuit_breaker_cooldown(),
+            max_backoff: default_max_backoff(),
+            shutdown_timeout: default_shutdown_timeout(),
+            log_format: LogFormat::default(),
+            verbose: legacy.verbose,
+            log_file: legacy.log_file.clone(),
+            custom_handlers: HashMap::new(),
+            webhook_port: None,
+            webhook_secret: None,
+            webhook_provider: None,
+            ssh_key_file: None,
+            notifiers: Vec::new(),
+            status_file: default_status_file(),
         };
         
         Self {
             global_settings: global,
             services: vec![service],
+            source: None,
         }
     }
 }
 
+/// Check that a checkout directory could be created: its nearest existing ancestor must be a directory
+fn check_local_path(path: &Path) -> Result<()> {
+    if path.as_os_str().is_empty() {
+        return Err(anyhow!("path is empty"));
+    }
+    
+    match path.ancestors().skip(1).find(|ancestor| ancestor.exists()) {
+        Some(ancestor) if !ancestor.is_dir() => Err(anyhow!("{} is not a directory", ancestor.display())),
+        _ => Ok(()),
+    }
+}
+
 /// Load configurations from various sources (JSON, environment variables)
 impl Config {
-    /// Load configuration, trying multi-service JSON first, then falling back to legacy config
+    /// Load configuration, trying a multi-service config file first, then falling back to legacy config
+    // Entry point for library users; the binary passes its command-line options to load_from
+    #[allow(dead_code)]
     pub fn load() -> Result<Self> {
+        Self::load_from(None, None)
+    }
+    
+    /// Load and validate the configuration from `path` (or `SERVICES_CONFIG` if not given),
+    /// where `-` reads stdin in `format`
+    pub fn load_from(path: Option<&str>, format: Option<ConfigFormat>) -> Result<Self> {
+        let config = Self::read_from(path, format)?;
+        config.ensure_valid()?;
+        Ok(config)
+    }
+    
+    /// Read the configuration from its source without validating it
+    fn read_from(path: Option<&str>, format: Option<ConfigFormat>) -> Result<Self> {
+        let services_config_path = path.map(String::from).or_else(|| env::var("SERVICES_CONFIG").ok());
+        
+        if services_config_path.as_deref() == Some(STDIN_CONFIG) {
+            info!("Loading multi-service configuration from stdin");
+            Self::warn_ignored_legacy_env();
+            return Self::load_from_stdin(format.unwrap_or_default());
+        }
+        
         // First, check if SERVICES_CONFIG env var is set and points to a valid file
-        if let Ok(services_config_path) = env::var("SERVICES_CONFIG") {
+        if let Some(services_config_path) = services_config_path {
             let path = Path::new(&services_config_path);
             if path.exists() {
                 info!("Loading multi-service configuration from {}", path.display());
-                return Self::load_from_json(path);
+                Self::warn_ignored_legacy_env();
+                return Self::load_from_file(path);
             } else {
                 warn!("Services config file {} not found, falling back to legacy config", path.display());
             }
+        } else if let Some(path) = Self::find_config_file() {
+            info!("Loading multi-service configuration from {}", path.display());
+            Self::warn_ignored_legacy_env();
+            return Self::load_from_file(&path);
         }
         
-        // If no services.json, fall back to legacy config
+        // If no services config file, fall back to legacy config
+        warn!("Configuration through environment variables is deprecated; move these settings to a services file");
+        Self::load_legacy()
+    }
+    
+    /// Load the legacy config from the environment (and `.env` file), converted to the new format
+    pub fn load_legacy() -> Result<Self> {
+        Self::load_env_file();
         info!("Loading legacy configuration from environment variables");
         let legacy_config = Self::load_legacy_from_env()?;
         
@@ -363,23 +1226,144 @@ impl Config {
         Ok(Config::from(&legacy_config))
     }
     
+    /// Save the configuration to a file as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize configuration")?;
+        
+        fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write configuration to {}", path.display()))
+    }
+    
+    /// Source a `.env` file (`WATCHER_ENV_FILE`, default `./.env`) into the environment for the legacy config
+    fn load_env_file() {
+        let explicit = env::var("WATCHER_ENV_FILE").ok();
+        let path = PathBuf::from(explicit.as_deref().unwrap_or(".env"));
+        
+        if !path.exists() {
+            if explicit.is_some() {
+                warn!("Env file {} not found, using the process environment only", path.display());
+            }
+            return;
+        }
+        
+        // Variables already set in the environment take precedence over the file
+        match dotenvy::from_path(&path) {
+            Ok(()) => info!("Loaded environment variables from {}", path.display()),
+            Err(e) => warn!("Failed to load env file {}: {}", path.display(), e),
+        }
+    }
+    
+    /// Default locations searched for a services config file, in order of precedence
+    pub fn default_config_paths() -> Vec<PathBuf> {
+        let mut dirs = vec![PathBuf::from(".")];
+        
+        // User config directory ($XDG_CONFIG_HOME, or the platform equivalent)
+        let user_config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(dirs::config_dir);
+        if let Some(dir) = user_config_dir {
+            dirs.push(dir.join("watcher"));
+        }
+        
+        dirs.push(PathBuf::from("/etc/watcher"));
+        
+        dirs.iter()
+            .flat_map(|dir| CONFIG_EXTENSIONS.iter().map(move |ext| dir.join(format!("services.{}", ext))))
+            .collect()
+    }
+    
+    /// Find the first existing services config file in the default locations
+    pub fn find_config_file() -> Option<PathBuf> {
+        Self::default_config_paths().into_iter().find(|path| path.is_file())
+    }
+    
+    /// Load multi-service config from a file, choosing the parser from its extension
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let extension = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        
+        match extension.as_deref() {
+            Some("toml") => Self::load_from_toml(path),
+            Some("yaml") | Some("yml") => Self::load_from_yaml(path),
+            Some("json") | None => Self::load_from_json(path),
+            Some(other) => Err(anyhow!("Unsupported config file format '{}' (expected .json, .toml, .yaml or .yml): {}", 
+                                       other, path.display())),
+        }
+    }
+    
     /// Load multi-service config from a JSON file
     pub fn load_from_json(path: &Path) -> Result<Self> {
+        Self::load_file_as(path, ConfigFormat::Json)
+    }
+    
+    /// Load multi-service config from a TOML file
+    pub fn load_from_toml(path: &Path) -> Result<Self> {
+        Self::load_file_as(path, ConfigFormat::Toml)
+    }
+    
+    /// Load multi-service config from a YAML file
+    pub fn load_from_yaml(path: &Path) -> Result<Self> {
+        Self::load_file_as(path, ConfigFormat::Yaml)
+    }
+    
+    /// Load multi-service config from a file in the given format
+    fn load_file_as(path: &Path, format: ConfigFormat) -> Result<Self> {
         let file_content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read services config file: {}", path.display()))?;
-            
-        let config: Config = serde_json::from_str(&file_content)
+        
+        let mut config = Self::parse(&file_content, format)
             .with_context(|| format!("Failed to parse services config file: {}", path.display()))?;
-            
+        
+        config.source = Some(path.to_path_buf());
+        Ok(config.with_default_service())
+    }
+    
+    /// Load multi-service config piped to stdin, so secrets never have to touch the disk
+    pub fn load_from_stdin(format: ConfigFormat) -> Result<Self> {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)
+            .context("Failed to read services config from stdin")?;
+        
+        let config = Self::parse(&content, format)
+            .context("Failed to parse services config from stdin")?;
+        
+        Ok(config.with_default_service())
+    }
+    
+    /// Parse a services config in the given format
+    fn parse(content: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Json => Ok(serde_json::from_str(content)?),
+            ConfigFormat::Toml => Ok(toml::from_str(content)?),
+            ConfigFormat::Yaml => Ok(serde_yaml::from_str(content)?),
+        }
+    }
+    
+    /// Make sure at least one service exists
+    fn with_default_service(mut self) -> Self {
         // Validate at least one service exists
-        if config.services.is_empty() {
+        if self.services.is_empty() {
             warn!("No services defined in config file. Adding default nginx service.");
-            let mut config = config;
-            config.services.push(ServiceConfig::default_nginx());
-            return Ok(config);
+            self.services.push(ServiceConfig::default_nginx());
         }
         
-        Ok(config)
+        self
+    }
+    
+    /// Warn about legacy environment variables that are ignored because a services file was found
+    fn warn_ignored_legacy_env() {
+        let ignored: Vec<String> = LEGACY_ENV_VARS.iter()
+            .filter(|(var, _)| env::var_os(var).is_some())
+            .map(|(var, replacement)| format!("{} (use {})", var, replacement))
+            .collect();
+        
+        if !ignored.is_empty() {
+            warn!("Ignoring deprecated legacy environment variables because a services file is in use: {}",
+                  ignored.join(", "));
+        }
     }
     
     /// Load legacy config from environment variables
@@ -429,6 +1413,12 @@ impl Config {
             config.verbose = value.to_lowercase() == "true";
         }
         
+        if let Ok(value) = env::var("LOG_FILE") {
+            if !value.is_empty() {
+                config.log_file = Some(value);
+            }
+        }
+        
         if let Ok(value) = env::var("DISABLE_RESTART") {
             config.disable_restart = value.to_lowercase() == "true";
         }
@@ -482,15 +1472,160 @@ impl Config {
         Ok(config)
     }
     
+    /// Validate the configuration before it is applied, reporting every problem found
+    pub fn validate(&self) -> std::result::Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        let mut names = HashSet::new();
+        let mut containers = HashMap::new();
+        
+        if let Err(e) = parse_duration(&self.global_settings.startup_grace_period) {
+            problems.push(format!("Invalid global startup_grace_period: {}", e));
+        }
+        
+        for service in &self.services {
+            if service.name.is_empty() {
+                problems.push(format!("Service for repository {} has an empty name", service.repo_url));
+            }
+            
+            // Service names identify running tasks, so they must be unique
+            if !service.name.is_empty() && !names.insert(service.name.as_str()) {
+                problems.push(format!("Duplicate service name: {}", service.name));
+            }
+            
+            // Two services restarting one container fight over it
+            if !service.container_name.is_empty() {
+                if let Some(other) = containers.insert(service.container_name.as_str(), service.name.as_str()) {
+                    problems.push(format!("Services {} and {} use the same container {}", 
+                                          other, service.name, service.container_name));
+                }
+            }
+            
+            if service.repo_url.trim().is_empty() {
+                problems.push(format!("Service {} has an empty repo_url", service.name));
+            }
+            
+            if let Err(e) = check_local_path(&service.local_path) {
+                problems.push(format!("Service {} has an unusable local_path {}: {}", 
+                                      service.name, service.local_path.display(), e));
+            }
+            
+            for (field, url) in [("healthcheck_url", &service.healthcheck_url), ("health_url", &service.health_url)] {
+                if let Some(Err(e)) = url.as_deref().map(url::Url::parse) {
+                    problems.push(format!("Service {} has an invalid {}: {}", service.name, field, e));
+                }
+            }
+            
+            if let Some(Err(e)) = service.startup_grace_period.as_deref().map(parse_duration) {
+                problems.push(format!("Service {} has an invalid startup_grace_period: {}", service.name, e));
+            }
+        }
+        
+        // Every repository of a service needs its own checkout
+        for service in &self.services {
+            let mut paths = HashSet::from([&service.local_path]);
+            for extra in &service.extra_repos {
+                if !paths.insert(&extra.local_path) {
+                    problems.push(format!("Service {} checks out more than one repository into {}", 
+                                          service.name, extra.local_path.display()));
+                }
+            }
+        }
+        
+        // Error patterns of log files are compiled on every check, so catch typos up front
+        for service in &self.services {
+            for log_file in &service.log_files {
+                if let Err(e) = log_file.error_regexes() {
+                    problems.push(format!("Service {} has an invalid log file: {:#}", service.name, e));
+                }
+            }
+        }
+        
+        // A staged checkout is never visible inside the container
+        for service in &self.services {
+            if service.stage_before_apply && service.validation_in_container {
+                problems.push(format!("Service {} cannot use stage_before_apply with validation_in_container", service.name));
+            }
+        }
+        
+        // A branch can only be checked out in one worktree of a shared clone
+        let mut shared_branches = HashSet::new();
+        for service in self.services.iter().filter(|service| service.shared_clone) {
+            let branch = service.effective_branch(&self.global_settings.default_branch);
+            if !shared_branches.insert((service.repo_url.as_str(), branch.clone())) {
+                problems.push(format!("Service {} shares a clone of {} with another service on branch {}", 
+                                      service.name, service.repo_url, branch));
+            }
+        }
+        
+        // Restart ordering must refer to known services and must not loop
+        for service in &self.services {
+            for dependency in &service.depends_on {
+                if !names.contains(dependency.as_str()) {
+                    problems.push(format!("Service {} depends on unknown service {}", service.name, dependency));
+                }
+            }
+        }
+        
+        // Every service on a cycle would report it, so only the first is kept
+        if let Some(e) = self.services.iter().find_map(|service| self.check_dependency_cycle(&service.name, &mut vec![]).err()) {
+            problems.push(e.to_string());
+        }
+        
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+    
+    /// Validate the configuration, failing with a list of every problem found
+    pub fn ensure_valid(&self) -> Result<()> {
+        self.validate().map_err(|problems| {
+            let list: Vec<String> = problems.iter().map(|problem| format!("  - {}", problem)).collect();
+            anyhow!("Invalid configuration ({} problems):\n{}", problems.len(), list.join("\n"))
+        })
+    }
+    
+    /// Walk the depends_on graph from a service, failing if it leads back to a service on the path
+    fn check_dependency_cycle<'a>(&'a self, name: &'a str, path: &mut Vec<&'a str>) -> Result<()> {
+        if path.contains(&name) {
+            path.push(name);
+            return Err(anyhow!("Circular service dependency: {}", path.join(" -> ")));
+        }
+        
+        path.push(name);
+        if let Some(service) = self.services.iter().find(|s| s.name == name) {
+            for dependency in &service.depends_on {
+                self.check_dependency_cycle(dependency, path)?;
+            }
+        }
+        path.pop();
+        
+        Ok(())
+    }
+    
     /// Display configuration in a human-readable format
     pub fn display(&self) {
+        match &self.source {
+            Some(path) => info!("Configuration loaded from {}", path.display()),
+            None => info!("Configuration loaded from environment variables"),
+        }
+        
         info!("== Global Configuration ==");
         info!("Watch Interval: {} seconds", self.global_settings.watch_interval);
         info!("Default Branch: {}", self.global_settings.default_branch);
         info!("Default Auto Fix: {}", self.global_settings.auto_fix);
+        if let Some(revert) = self.global_settings.revert_on_failure {
+            info!("Default Revert On Failure: {}", revert);
+        }
         info!("Default Fix Permissions: {}", self.global_settings.fix_permissions);
+        if self.global_settings.allow_unsafe_permission_paths {
+            warn!("Unsafe permission paths allowed: permissions may be changed on system directories");
+        }
         info!("Default Monitor Logs: {}", self.global_settings.monitor_logs);
+        info!("Default Log Error Examples: {}", self.global_settings.log_error_examples);
         info!("Default Disable Restart: {}", self.global_settings.disable_restart);
+        info!("Container Runtime: {:?}", self.global_settings.container_runtime);
         info!("Default Use Docker Compose: {}", self.global_settings.use_docker_compose);
         
         if let Some(dir) = &self.global_settings.default_compose_dir {
@@ -501,7 +1636,68 @@ impl Config {
             info!("Default Compose File: {}", file);
         }
         
-        info!("Startup Grace Period: {}", self.global_settings.startup_grace_period);
+        if self.global_settings.skip_startup_grace {
+            info!("Startup Grace Period: skipped");
+        } else {
+            info!("Startup Grace Period: {}", self.global_settings.startup_grace_period);
+        }
+        info!("Log Format: {:?}", self.global_settings.log_format);
+        info!("Verbose: {}", self.global_settings.verbose);
+        
+        if let Some(file) = &self.global_settings.log_file {
+            info!("Log File: {}", file);
+        }
+        
+        info!("Retry Attempts: {} (base delay {})", self.global_settings.retry_attempts, self.global_settings.retry_delay);
+        info!("Git Timeout: {}", self.global_settings.git_timeout);
+        info!("Container Ready Timeout: {}", self.global_settings.container_ready_timeout);
+        info!("Restarting Grace Period: {}", self.global_settings.restarting_grace_period);
+        info!("Shared Clone Directory: {}", self.global_settings.shared_clone_dir.display());
+        if self.global_settings.run_as_user.is_some() || self.global_settings.run_as_group.is_some() {
+            info!("Run As: user {:?}, group {:?}", self.global_settings.run_as_user, self.global_settings.run_as_group);
+        }
+        info!("Notification Window: {}", self.global_settings.notification_window);
+        info!("Healthcheck Attempts: {} (timeout {} each)", self.global_settings.healthcheck_attempts, self.global_settings.healthcheck_timeout);
+        info!("Cycle Timeout: {}s", self.global_settings.cycle_timeout_duration().as_secs());
+        info!("Max Backoff: {}", self.global_settings.max_backoff);
+        info!("Shutdown Timeout: {}", self.global_settings.shutdown_timeout);
+        if self.global_settings.max_failures_before_exit > 0 {
+            info!("Max Failures Before Exit: {}", self.global_settings.max_failures_before_exit);
+        }
+        if self.global_settings.circuit_breaker_threshold > 0 {
+            info!("Circuit Breaker: {} validation failures, {} cooldown", 
+                  self.global_settings.circuit_breaker_threshold, self.global_settings.circuit_breaker_cooldown);
+        }
+        for (name, handler) in &self.global_settings.custom_handlers {
+            info!("Custom Handler '{}': validate={:?}, restart={:?}, monitor={:?}, health={:?}", 
+                  name, handler.validate_command, handler.restart_command, handler.monitor_command, handler.health_command);
+        }
+        
+        if let Some(port) = self.global_settings.webhook_port {
+            info!("Webhook Port: {} (signature verification {})", port,
+                  if self.global_settings.webhook_secret.is_some() { "enabled" } else { "disabled" });
+            match self.global_settings.webhook_provider {
+                Some(provider) => info!("Webhook Provider: {:?}", provider),
+                None => info!("Webhook Provider: detected from request headers"),
+            }
+        }
+        
+        if let Some(path) = &self.global_settings.ssh_key_file {
+            info!("SSH Key File: {}", path.display());
+        }
+        
+        if let Some(path) = &self.global_settings.status_file {
+            info!("Status File: {}", path.display());
+        }
+        
+        for notifier in &self.global_settings.notifiers {
+            // Only the kind is shown, since webhook URLs usually embed a secret
+            match notifier {
+                NotifierConfig::Slack { .. } => info!("Notifier: slack"),
+                NotifierConfig::Webhook { .. } => info!("Notifier: webhook"),
+            }
+        }
+        
         info!("Number of Services: {}", self.services.len());
         
         for (i, service) in self.services.iter().enumerate() {
@@ -510,17 +1706,73 @@ impl Config {
             info!("Container: {}", service.container_name);
             info!("Type: {:?}", service.service_type);
             info!("Repository URL: {}", service.repo_url);
+            for mirror in &service.mirror_urls {
+                info!("Mirror URL: {}", mirror);
+            }
             info!("Branch: {}", service.effective_branch(&self.global_settings.default_branch));
             info!("Config Directory: {}", service.local_path.display());
+            for extra in &service.extra_repos {
+                info!("Extra Repository: {} ({}) in {}", extra.repo_url, 
+                      extra.effective_branch(service, &self.global_settings.default_branch), extra.local_path.display());
+            }
+            if !service.notify_branches.is_empty() {
+                info!("Notify Branches: {}", service.notify_branches.join(", "));
+            }
+            
+            if let Some(commit) = &service.pinned_commit {
+                warn!("Pinned Commit: {} (branch updates are ignored)", commit);
+            }
+            
+            if !service.watch {
+                info!("Watch: disabled (initial sync only)");
+            }
             
-            info!("Docker Compose: {}", service.use_docker_compose || self.global_settings.use_docker_compose);
+            if let Some(sparse) = &service.sparse_path {
+                info!("Sparse Path: {}", sparse);
+            }
+            
+            if service.conflict_strategy != ConflictStrategy::Abort {
+                info!("Conflict Strategy: {:?}", service.conflict_strategy);
+            }
+            
+            if service.local_changes_policy != LocalChangesPolicy::Stash {
+                info!("Local Changes Policy: {:?}", service.local_changes_policy);
+            }
+            
+            if service.clone_into_nonempty != NonEmptyDirPolicy::Backup {
+                info!("Clone Into Non-Empty Directory: {:?}", service.clone_into_nonempty);
+            }
+            
+            if service.shared_clone {
+                info!("Shared Clone: yes (worktree of a bare clone in {})", self.global_settings.shared_clone_dir.display());
+            }
+            
+            if !service.watch_paths.is_empty() {
+                info!("Watched Paths: {}", service.watch_paths.join(", "));
+            }
+            
+            if service.service_type == ServiceType::Kubernetes {
+                info!("Namespace: {}", service.effective_namespace());
+                info!("Deployment: {}", service.effective_deployment());
+            } else {
+                info!("Docker Compose: {}", service.use_docker_compose || self.global_settings.use_docker_compose);
+            }
             
             if let Some(dir) = service.get_compose_dir(&self.global_settings.default_compose_dir) {
                 info!("Compose Directory: {}", dir.display());
             }
             
-            if let Some(file) = service.get_compose_file(&self.global_settings.default_compose_file) {
-                info!("Compose File: {}", file);
+            let compose_files = service.get_compose_files(&self.global_settings.default_compose_file);
+            if !compose_files.is_empty() {
+                info!("Compose Files: {}", compose_files.join(", "));
+            }
+            
+            if !service.compose_profiles.is_empty() {
+                info!("Compose Profiles: {}", service.compose_profiles.join(", "));
+            }
+            
+            if let Some(env_file) = &service.env_file {
+                info!("Compose Env File: {}", env_file.display());
             }
             
             if let Some(cmd) = &service.restart_command {
@@ -528,7 +1780,71 @@ impl Config {
             }
             
             if let Some(cmd) = &service.validation_command {
-                info!("Validation Command: {}", cmd);
+                let location = if service.validation_in_container { " (in container)" } else { "" };
+                info!("Validation Command: {}{}", cmd, location);
+            }
+            
+            if service.keep_stopped_on_invalid {
+                info!("Keep Stopped On Invalid: yes");
+            }
+            
+            if service.read_only {
+                info!("Read Only: drift is reported, updates are never applied");
+            }
+            
+            if service.stage_before_apply {
+                info!("Stage Before Apply: yes");
+            }
+            
+            if service.tag_deploys {
+                let push = if service.push_deploy_tags { ", pushed to the remote" } else { "" };
+                info!("Tag Deploys: yes{}", push);
+            }
+            
+            if let Some(cmd) = &service.readiness_command {
+                info!("Readiness Command: {}", cmd);
+            }
+            
+            if let Some(cmd) = &service.health_command {
+                info!("Health Command: {}", cmd);
+            }
+            
+            if let Some(url) = &service.health_url {
+                info!("Health URL: {}", url);
+                if service.health_check_attempts > 0 {
+                    info!("Post-Restart Health Checks: {} every {}", service.health_check_attempts, service.health_check_delay);
+                }
+            }
+            
+            if service.readiness_attempts > 0 {
+                info!("Readiness Checks: {} every {}", service.readiness_attempts, service.readiness_delay);
+            }
+            
+            if !service.expected_networks.is_empty() {
+                info!("Expected Networks: {}", service.expected_networks.join(", "));
+            }
+            
+            if let Some(cmd) = &service.pre_apply_command {
+                info!("Pre-Apply Command: {}", cmd);
+            }
+            
+            if let Some(cmd) = &service.post_apply_command {
+                info!("Post-Apply Command: {}", cmd);
+            }
+            
+            if let Some(dir) = &service.command_cwd {
+                info!("Command Directory: {}", dir.display());
+            }
+            
+            if !service.command_env.is_empty() {
+                // Values may hold credentials, so only the names are shown
+                let mut names: Vec<&str> = service.command_env.keys().map(String::as_str).collect();
+                names.sort();
+                info!("Command Environment: {}", names.join(", "));
+            }
+            
+            if !service.depends_on.is_empty() {
+                info!("Depends On: {}", service.depends_on.join(", "));
             }
             
             info!("Disable Restart: {}", service.disable_restart);
@@ -538,8 +1854,26 @@ impl Config {
             }
             
             info!("Auto Fix: {}", service.effective_auto_fix(self.global_settings.auto_fix));
+            info!("Revert On Failure: {}", service.effective_revert_on_failure(&self.global_settings));
             info!("Monitor Logs: {}", service.effective_monitor_logs(self.global_settings.monitor_logs));
             info!("Log Tail Lines: {}", service.log_tail_lines);
+            info!("Log Error Examples: {}", service.effective_log_error_examples(&self.global_settings));
+            if service.log_streams != LogStreams::Both {
+                info!("Log Streams: {:?}", service.log_streams);
+            }
+            
+            if !service.log_files.is_empty() {
+                let paths: Vec<&str> = service.log_files.iter().map(|log_file| log_file.path.as_str()).collect();
+                info!("Log Files: {}", paths.join(", "));
+            }
+            
+            if service.monitor_resources {
+                info!("Resource Limits: CPU {}%, memory {}%", service.max_cpu_percent, service.max_memory_percent);
+            }
+            
+            if let Some(period) = &service.startup_grace_period {
+                info!("Startup Grace Period: {}", period);
+            }
             info!("Fix Permissions: {}", service.effective_fix_permissions(self.global_settings.fix_permissions));
             
             if let Some(perms) = &service.permissions {
@@ -556,6 +1890,54 @@ impl Config {
         }
     }
     
+    /// Print the settings that apply to each service once the global defaults are merged in
+    pub fn display_effective(&self) {
+        let global = &self.global_settings;
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        
+        for service in &self.services {
+            let compose = service.use_docker_compose || global.use_docker_compose;
+            
+            println!("== {} ==", service.name);
+            println!("  Type:                  {:?}", service.service_type);
+            println!("  Repository:            {}", service.repo_url);
+            match &service.pinned_commit {
+                Some(commit) => println!("  Pinned Commit:         {}", commit),
+                None => println!("  Branch:                {}", service.effective_branch(&global.default_branch)),
+            }
+            println!("  Config Directory:      {}", service.config_dir().display());
+            println!("  Watch:                 {}", if service.watch { "yes" } else { "init only" });
+            println!("  Watch Interval:        {} seconds", global.watch_interval);
+            println!("  Startup Grace Period:  {} seconds", service.effective_startup_grace_period(global).as_secs());
+            if service.service_type == ServiceType::Kubernetes {
+                println!("  Deployment:            {}/{}", service.effective_namespace(), service.effective_deployment());
+            } else {
+                println!("  Container:             {}", service.container_name);
+            }
+            println!("  Auto Fix:              {}", yes_no(service.effective_auto_fix(global.auto_fix)));
+            println!("  Revert On Failure:     {}", yes_no(service.effective_revert_on_failure(global)));
+            println!("  Fix Permissions:       {}", yes_no(service.effective_fix_permissions(global.fix_permissions)));
+            println!("  Monitor Logs:          {}", yes_no(service.effective_monitor_logs(global.monitor_logs)));
+            println!("  Log Error Examples:    {}", service.effective_log_error_examples(global));
+            println!("  Restart:               {}", if service.disable_restart || global.disable_restart { "disabled" } else { "enabled" });
+            println!("  Use Docker Compose:    {}", yes_no(compose));
+            if compose {
+                match service.get_compose_dir(&global.default_compose_dir) {
+                    Some(dir) => println!("  Compose Directory:     {}", dir.display()),
+                    None => println!("  Compose Directory:     {} (or the working directory if missing)", service.config_dir().display()),
+                }
+                let files = service.get_compose_files(&global.default_compose_file);
+                if files.is_empty() {
+                    println!("  Compose Files:         compose defaults");
+                } else {
+                    println!("  Compose Files:         {}", files.join(", "));
+                }
+            }
+            println!("  Validation Command:    {}", service.effective_validation_command(global).as_deref().unwrap_or("none"));
+            println!("  Restart Command:       {}", service.effective_restart_command(global).as_deref().unwrap_or("none"));
+        }
+    }
+    
     /// Create a simplified Nginx config for the docker module
     pub fn to_nginx_config(&self, service_idx: usize) -> Result<nginx::Config> {
         if service_idx >= self.services.len() {
@@ -572,18 +1954,25 @@ impl Config {
         let compose_dir = service.get_compose_dir(&self.global_settings.default_compose_dir)
             .unwrap_or_else(|| PathBuf::from("."));
             
-        let compose_file = service.get_compose_file(&self.global_settings.default_compose_file)
-            .unwrap_or_else(|| "docker-compose.yml".to_string());
+        let compose_files = service.get_compose_files(&self.global_settings.default_compose_file);
         
         Ok(nginx::Config {
             nginx_container_name: service.container_name.clone(),
             compose_dir,
-            compose_file,
+            compose_files,
+            compose_profiles: service.compose_profiles.clone(),
+            env_file: service.env_file.clone(),
             use_docker_compose: service.use_docker_compose || self.global_settings.use_docker_compose,
             disable_restart: service.disable_restart || self.global_settings.disable_restart,
             monitor_logs: service.effective_monitor_logs(self.global_settings.monitor_logs),
             log_tail_lines: service.log_tail_lines,
+            log_error_examples: service.effective_log_error_examples(&self.global_settings),
+            log_streams: service.log_streams,
             force_rebuild: None,
+            retry_attempts: self.global_settings.retry_attempts,
+            retry_delay: self.global_settings.retry_delay_duration(),
+            ready_timeout: self.global_settings.container_ready_timeout_duration(),
+            restarting_grace: self.global_settings.restarting_grace_period_duration(),
         })
     }
     
@@ -597,18 +1986,25 @@ impl Config {
         let compose_dir = service.get_compose_dir(&global.default_compose_dir)
             .unwrap_or_else(|| PathBuf::from("."));
             
-        let compose_file = service.get_compose_file(&global.default_compose_file)
-            .unwrap_or_else(|| "docker-compose.yml".to_string());
+        let compose_files = service.get_compose_files(&global.default_compose_file);
         
         Ok(nginx::Config {
             nginx_container_name: service.container_name.clone(),
             compose_dir,
-            compose_file,
+            compose_files,
+            compose_profiles: service.compose_profiles.clone(),
+            env_file: service.env_file.clone(),
             use_docker_compose: service.use_docker_compose || global.use_docker_compose,
             disable_restart: service.disable_restart || global.disable_restart,
             monitor_logs: service.effective_monitor_logs(global.monitor_logs),
             log_tail_lines: service.log_tail_lines,
+            log_error_examples: service.effective_log_error_examples(global),
+            log_streams: service.log_streams,
             force_rebuild: None,
+            retry_attempts: global.retry_attempts,
+            retry_delay: global.retry_delay_duration(),
+            ready_timeout: global.container_ready_timeout_duration(),
+            restarting_grace: global.restarting_grace_period_duration(),
         })
     }
 }
@@ -616,17 +2012,128 @@ impl Config {
 // Module declaration for nginx to avoid circular dependencies
 pub mod nginx {
     use std::path::PathBuf;
+    use std::time::Duration;
     use serde::{Deserialize, Serialize};
     
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct Config {
         pub nginx_container_name: String,
         pub compose_dir: PathBuf,
-        pub compose_file: String,
+        pub compose_files: Vec<String>,
+        pub compose_profiles: Vec<String>,
+        pub env_file: Option<PathBuf>,
         pub use_docker_compose: bool,
         pub disable_restart: bool,
         pub monitor_logs: bool,
         pub log_tail_lines: u32,
+        pub log_error_examples: usize,
+        pub log_streams: super::LogStreams,
         pub force_rebuild: Option<bool>,
+        pub retry_attempts: u32,
+        pub retry_delay: Duration,
+        pub ready_timeout: Duration,
+        pub restarting_grace: Duration,
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_save_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("services.json");
+        let config = Config::from(&LegacyConfig::default());
+        
+        config.save(&path).unwrap();
+        let loaded = Config::load_from_file(&path).unwrap();
+        
+        assert_eq!(loaded.services, config.services);
+        assert_eq!(loaded.global_settings, config.global_settings);
+        assert_eq!(loaded.source, Some(path));
+    }
+    
+    #[test]
+    fn test_load_yaml_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml = "services:\n  - name: web\n    repo_url: https://example.com/web.git\n    local_path: /srv/web\n    container_name: web\nglobal_settings:\n  watch_interval: 30\n";
+        
+        for name in ["services.yaml", "services.yml"] {
+            let path = dir.path().join(name);
+            fs::write(&path, yaml).unwrap();
+            
+            let config = Config::load_from_file(&path).unwrap();
+            assert_eq!(config.services.len(), 1);
+            assert_eq!(config.services[0].name, "web");
+            assert_eq!(config.global_settings.watch_interval, 30);
+        }
+        
+        // An empty services list still gets the default nginx service
+        let path = dir.path().join("empty.yaml");
+        fs::write(&path, "services: []\n").unwrap();
+        assert_eq!(Config::load_from_file(&path).unwrap().services, vec![ServiceConfig::default_nginx()]);
+        
+        let path = dir.path().join("services.ini");
+        fs::write(&path, "").unwrap();
+        assert!(Config::load_from_file(&path).is_err());
+    }
+    
+    #[test]
+    fn test_container_runtime() {
+        let global: GlobalSettings = serde_json::from_str(r#"{"container_runtime": "podman"}"#).unwrap();
+        assert_eq!(global.container_runtime, ContainerRuntime::Podman);
+        assert_eq!(global.container_runtime.binary(), "podman");
+        assert_eq!(global.container_runtime.compose_binary(), "podman-compose");
+        assert_eq!(GlobalSettings::default().container_runtime.binary(), "docker");
+    }
+    
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        
+        let mut config = Config {
+            services: vec![ServiceConfig::default_nginx(), ServiceConfig::default_nginx()],
+            global_settings: GlobalSettings::default(),
+            source: None,
+        };
+        config.services[1].name = "other".to_string();
+        config.services[1].local_path = dir.path().join("other");
+        assert!(config.validate().is_err_and(|problems| problems.len() == 1 && problems[0].contains("same container")));
+        
+        config.services[1].container_name = "other".to_string();
+        config.services[1].repo_url = String::new();
+        config.services[1].healthcheck_url = Some("not a url".to_string());
+        config.services[1].startup_grace_period = Some("soon".to_string());
+        config.services[1].local_path = file.join("other");
+        config.services[1].depends_on = vec!["missing".to_string()];
+        
+        let problems = config.validate().unwrap_err();
+        for expected in ["empty repo_url", "invalid healthcheck_url", "invalid startup_grace_period", "unusable local_path", "unknown service"] {
+            assert!(problems.iter().any(|problem| problem.contains(expected)), "missing {:?} in {:?}", expected, problems);
+        }
+        
+        // Loading reports the same problems
+        let path = dir.path().join("services.json");
+        config.save(&path).unwrap();
+        let error = Config::load_from(path.to_str(), None).unwrap_err().to_string();
+        assert!(error.contains(&format!("({} problems)", problems.len())), "{}", error);
+    }
+    
+    #[test]
+    fn test_log_file_error_patterns() {
+        let mut log_file = LogFile { path: "/var/log/app.log".to_string(), error_patterns: vec![] };
+        let default = log_file.error_regexes().unwrap();
+        assert!(default[0].is_match("2024-01-01 ERROR disk full"));
+        
+        log_file.error_patterns = vec![r"\bFATAL\b".to_string(), "panic".to_string()];
+        let patterns = log_file.error_regexes().unwrap();
+        assert!(patterns.iter().any(|pattern| pattern.is_match("thread main panicked")));
+        assert!(!patterns.iter().any(|pattern| pattern.is_match("request error handled")));
+        
+        log_file.error_patterns = vec!["(unclosed".to_string()];
+        assert!(log_file.error_regexes().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::config::{CustomHandler, GlobalSettings, ServiceConfig, ServiceType};
+use crate::service::{check_health, restart_service, shell_command, ServiceHandler};
+use crate::utils::fix_permissions;
+
+/// Default command timeout in seconds
+const DEFAULT_COMMAND_TIMEOUT: u64 = 60;
+
+/// Service handler driven by the user-defined commands of a custom service type
+pub struct CustomCommandService<'a> {
+    service: &'a ServiceConfig,
+    global: &'a GlobalSettings,
+    handler: &'a CustomHandler,
+}
+
+impl<'a> CustomCommandService<'a> {
+    /// Create a new CustomCommandService for a service with a registered custom type
+    pub fn new(service: &'a ServiceConfig, global: &'a GlobalSettings) -> Result<Self> {
+        let type_name = match &service.service_type {
+            ServiceType::Custom(name) => name,
+            _ => return Err(anyhow!("Service type mismatch: expected a custom service type")),
+        };
+        
+        let handler = global.custom_handlers.get(type_name)
+            .ok_or_else(|| anyhow!("No custom handler registered for service type '{}'", type_name))?;
+        
+        Ok(Self {
+            service,
+            global,
+            handler,
+        })
+    }
+    
+    /// Run a shell command, returning whether it succeeded and its combined output
+    async fn run_command(&self, cmd: &str) -> Result<(bool, String)> {
+        debug!(service = self.service.name; "Running command: {}", cmd);
+        
+        let output = timeout(
+            Duration::from_secs(DEFAULT_COMMAND_TIMEOUT),
+            shell_command(self.service, cmd).output()
+        ).await
+            .context(format!("Command timed out for service {}", self.service.name))?
+            .context(format!("Failed to execute command for service {}", self.service.name))?;
+        
+        let combined = format!("{}{}", 
+                               String::from_utf8_lossy(&output.stdout), 
+                               String::from_utf8_lossy(&output.stderr));
+        
+        Ok((output.status.success(), combined))
+    }
+}
+
+#[async_trait]
+impl<'a> ServiceHandler for CustomCommandService<'a> {
+    fn name(&self) -> &str {
+        &self.service.name
+    }
+    
+    fn service_type(&self) -> ServiceType {
+        self.service.service_type.clone()
+    }
+    
+    async fn validate(&self) -> Result<bool> {
+        let cmd = match self.service.effective_validation_command(self.global) {
+            Some(cmd) => cmd,
+            None => return Ok(true),
+        };
+        
+        info!(service = self.service.name; "Running validation command: {}", cmd);
+        let (success, output) = self.run_command(&cmd).await?;
+        
+        if !success {
+            warn!(service = self.service.name; "Validation command failed: {}", output.trim());
+        }
+        
+        Ok(success)
+    }
+    
+    async fn restart(&self) -> Result<()> {
+        // Honors the handler's restart command through the service's effective restart command
+        restart_service(self.service, self.global).await
+    }
+    
+    async fn fix_issues(&self) -> Result<()> {
+        // Custom service types have no built-in fixes
+        Ok(())
+    }
+    
+    async fn fix_permissions(&self) -> Result<()> {
+        if !self.service.effective_fix_permissions(self.global.fix_permissions) {
+            return Ok(());
+        }
+        
+        match &self.service.permissions {
+            Some(permissions) => fix_permissions(self.service, permissions, self.global).await,
+            None => Ok(()),
+        }
+    }
+    
+    async fn monitor(&self) -> Result<Vec<String>> {
+        let cmd = match &self.handler.monitor_command {
+            Some(cmd) => cmd,
+            None => return Ok(vec![]),
+        };
+        
+        let (success, output) = self.run_command(cmd).await?;
+        
+        // A successful monitor command means there is nothing to report
+        if success {
+            debug!(service = self.service.name; "Monitor command succeeded");
+            return Ok(vec![]);
+        }
+        
+        let mut problems = vec![format!("Monitor command failed: {}", cmd)];
+        problems.extend(output.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from));
+        
+        Ok(problems)
+    }
+    
+    async fn health(&self) -> Result<bool> {
+        check_health(self.service, self.global).await
+    }
+}
\ No newline at end of file
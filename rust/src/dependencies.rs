@@ -0,0 +1,89 @@
+use log::{info, warn};
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration, Instant};
+
+use crate::config::ServiceConfig;
+use crate::webhook;
+
+/// Longest a service waits for its dependencies before restarting anyway
+const DEPENDENCY_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Services with a check-and-apply cycle in progress or requested
+static BUSY: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Woken whenever a service finishes a cycle
+static CYCLE_DONE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Marks a service's cycle as in progress until dropped
+pub struct CycleGuard {
+    name: String,
+}
+
+impl Drop for CycleGuard {
+    fn drop(&mut self) {
+        if let Ok(mut busy) = BUSY.lock() {
+            busy.remove(&self.name);
+        }
+        CYCLE_DONE.notify_waiters();
+    }
+}
+
+/// Mark the start of a service's check-and-apply cycle
+pub fn begin_cycle(name: &str) -> CycleGuard {
+    if let Ok(mut busy) = BUSY.lock() {
+        busy.insert(name.to_string());
+    }
+    
+    CycleGuard { name: name.to_string() }
+}
+
+/// Get the dependencies of a service whose cycle has not finished yet
+fn busy_dependencies(service: &ServiceConfig) -> Vec<String> {
+    match BUSY.lock() {
+        Ok(busy) => service.depends_on.iter()
+            .filter(|dependency| busy.contains(*dependency))
+            .cloned()
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Have the dependencies of a service check for updates now, and wait until they have applied them
+///
+/// This orders restarts when several services update together, e.g. a proxy restarts after its backend.
+pub async fn wait_for_dependencies(service: &ServiceConfig) {
+    if service.depends_on.is_empty() {
+        return;
+    }
+    
+    // Dependencies pick up the same push before we restart; they are busy until their cycle ends
+    for dependency in &service.depends_on {
+        if let Ok(mut busy) = BUSY.lock() {
+            if webhook::trigger_service(dependency) {
+                busy.insert(dependency.clone());
+            }
+        }
+    }
+    
+    let deadline = Instant::now() + DEPENDENCY_WAIT_TIMEOUT;
+    loop {
+        // Register for the wakeup before checking, so a cycle ending in between is not missed
+        let done = CYCLE_DONE.notified();
+        tokio::pin!(done);
+        done.as_mut().enable();
+        
+        let pending = busy_dependencies(service);
+        if pending.is_empty() {
+            return;
+        }
+        
+        info!(service = service.name; "Waiting for dependencies to finish updating: {}", pending.join(", "));
+        if timeout(deadline.saturating_duration_since(Instant::now()), done).await.is_err() {
+            warn!(service = service.name; "Dependencies still updating after {} seconds, continuing: {}", 
+                                          DEPENDENCY_WAIT_TIMEOUT.as_secs(), pending.join(", "));
+            return;
+        }
+    }
+}
\ No newline at end of file
@@ -1,66 +1,228 @@
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
 use log::{debug, info, warn};
+use regex::Regex;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::{LazyLock, Mutex};
 use tokio::process::Command;
 use tokio::time::{sleep, Duration};
 
+use crate::config::{ContainerRuntime, GlobalSettings, LogStreams};
+
+/// Output of a container, kept apart by the stream it was written to
+#[derive(Debug, Clone, Default)]
+pub struct ContainerLogs {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ContainerLogs {
+    /// Lines of both streams, each labelled `stdout` or `stderr`
+    pub fn lines(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.stdout.lines().map(|line| ("stdout", line))
+            .chain(self.stderr.lines().map(|line| ("stderr", line)))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ContainerStatus {
     Running,
+    /// Docker is bringing the container back up, e.g. right after a compose restart
+    Restarting,
     Stopped,
     NotExists,
 }
 
-/// Check the current status of a Docker container
-pub async fn check_container_status(container_name: &str) -> Result<ContainerStatus> {
-    // Check running containers
-    let output = Command::new("docker")
-        .args(["ps", "--format", "{{.Names}}", "--filter", &format!("name=^{}$", container_name)])
-        .output()
-        .await
-        .context("Failed to execute docker ps command")?;
-    
-    let containers = String::from_utf8_lossy(&output.stdout).trim().to_string();
+/// Runtime whose CLI container commands are run with, set from the configuration
+static RUNTIME: Mutex<ContainerRuntime> = Mutex::new(ContainerRuntime::Docker);
+
+/// Use the configured `container_runtime` for all container commands
+pub fn set_container_runtime(global: &GlobalSettings) {
+    if let Ok(mut runtime) = RUNTIME.lock() {
+        *runtime = global.container_runtime;
+    }
+}
+
+/// Runtime container commands currently use
+pub fn container_runtime() -> ContainerRuntime {
+    RUNTIME.lock().map(|runtime| *runtime).unwrap_or_default()
+}
+
+/// Command running the CLI of the configured container runtime (`docker` or `podman`)
+pub fn container_command() -> Command {
+    Command::new(container_runtime().binary())
+}
+
+/// Container names resolved from patterns, cached until the container they point to disappears
+static RESOLVED_NAMES: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Resolve a container name that may contain `*`/`?` wildcards to a concrete container
+///
+/// Names without wildcards are returned unchanged. A pattern that matches no
+/// container is also returned unchanged so callers report it as missing.
+pub async fn resolve_container_name(container_name: &str) -> Result<String> {
+    if !container_name.contains(['*', '?']) {
+        return Ok(container_name.to_string());
+    }
     
-    if containers.contains(container_name) {
-        debug!("Container {} is running", container_name);
-        return Ok(ContainerStatus::Running);
+    if let Some(resolved) = RESOLVED_NAMES.lock()
+        .map_err(|_| anyhow!("Container name cache lock poisoned"))?
+        .get(container_name) {
+        return Ok(resolved.clone());
     }
     
-    // Check all containers (including stopped ones)
-    let output = Command::new("docker")
-        .args(["ps", "-a", "--format", "{{.Names}}", "--filter", &format!("name=^{}$", container_name)])
+    let output = container_command()
+        .args(["ps", "-a", "--format", "{{.Names}}"])
         .output()
         .await
         .context("Failed to execute docker ps -a command")?;
     
-    let containers = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let pattern = format!("^{}$", regex::escape(container_name)
+        .replace(r"\*", ".*")
+        .replace(r"\?", "."));
+    let pattern = Regex::new(&pattern)
+        .context(format!("Invalid container name pattern: {}", container_name))?;
+    
+    let names = String::from_utf8_lossy(&output.stdout);
+    let matches: Vec<&str> = names.lines()
+        .map(str::trim)
+        .filter(|name| pattern.is_match(name))
+        .collect();
+    
+    match matches.as_slice() {
+        [] => {
+            debug!("No container matches pattern {}", container_name);
+            Ok(container_name.to_string())
+        },
+        [name] => {
+            info!("Resolved container pattern {} to {}", container_name, name);
+            RESOLVED_NAMES.lock()
+                .map_err(|_| anyhow!("Container name cache lock poisoned"))?
+                .insert(container_name.to_string(), name.to_string());
+            Ok(name.to_string())
+        },
+        _ => Err(anyhow!("Container pattern {} is ambiguous, it matches: {}", 
+                         container_name, matches.join(", "))),
+    }
+}
+
+/// Drop the cached match of a container pattern, returning whether there was one
+fn forget_resolved_name(container_name: &str) -> bool {
+    RESOLVED_NAMES.lock()
+        .map(|mut names| names.remove(container_name).is_some())
+        .unwrap_or(false)
+}
+
+/// Drop every cached pattern match, e.g. after containers were recreated under new names
+fn forget_resolved_names() {
+    if let Ok(mut names) = RESOLVED_NAMES.lock() {
+        names.clear();
+    }
+}
+
+/// Check the current status of a Docker container
+pub async fn check_container_status(container_name: &str) -> Result<ContainerStatus> {
+    let status = inspect_container_status(&resolve_container_name(container_name).await?).await?;
     
-    if containers.contains(container_name) {
-        warn!("Container {} exists but is not running", container_name);
-        return Ok(ContainerStatus::Stopped);
+    // A cached pattern match goes stale when the container is recreated or a replica renamed
+    if status == ContainerStatus::NotExists && forget_resolved_name(container_name) {
+        debug!("Container resolved from pattern {} is gone, resolving it again", container_name);
+        return inspect_container_status(&resolve_container_name(container_name).await?).await;
     }
     
-    debug!("Container {} does not exist", container_name);
-    Ok(ContainerStatus::NotExists)
+    Ok(status)
 }
 
-/// Restart a Docker container or start it if stopped
-pub async fn restart_container(container_name: &str) -> Result<()> {
-    let status = check_container_status(container_name).await?;
+/// Status of a container by its concrete name
+async fn inspect_container_status(container_name: &str) -> Result<ContainerStatus> {
+    let output = container_command()
+        .args(["inspect", "--type", "container", "--format", "{{.State.Status}}", container_name])
+        .output()
+        .await
+        .context("Failed to execute docker inspect command")?;
+    
+    if !output.status.success() {
+        debug!("Container {} does not exist", container_name);
+        return Ok(ContainerStatus::NotExists);
+    }
     
-    match status {
-        ContainerStatus::Running => {
-            info!("Restarting running container {}", container_name);
-            execute_docker_command(&["restart", container_name], "restart").await?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "running" | "paused" => {
+            debug!("Container {} is running", container_name);
+            Ok(ContainerStatus::Running)
         },
-        ContainerStatus::Stopped => {
-            info!("Starting stopped container {}", container_name);
-            execute_docker_command(&["start", container_name], "start").await?;
+        "restarting" => {
+            debug!("Container {} is restarting", container_name);
+            Ok(ContainerStatus::Restarting)
         },
-        ContainerStatus::NotExists => {
-            return Err(anyhow!("Container {} does not exist and cannot be restarted", container_name));
+        state => {
+            warn!("Container {} exists but is not running ({})", container_name, state);
+            Ok(ContainerStatus::Stopped)
+        }
+    }
+}
+
+/// Re-check a restarting container until it settles or `grace` passes, returning the last status seen
+pub async fn wait_while_restarting(container_name: &str, grace: Duration) -> Result<ContainerStatus> {
+    let deadline = tokio::time::Instant::now() + grace;
+    
+    loop {
+        let status = check_container_status(container_name).await?;
+        if status != ContainerStatus::Restarting || tokio::time::Instant::now() + READY_POLL_INTERVAL > deadline {
+            return Ok(status);
+        }
+        
+        debug!("Container {} is restarting, re-checking in {} seconds", container_name, READY_POLL_INTERVAL.as_secs());
+        sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+/// Restart a Docker container or start it if stopped, retrying with backoff on failure
+///
+/// A container caught restarting is given `restarting_grace` to settle before anything is done to it.
+pub async fn restart_container(container_name: &str, max_attempts: u32, retry_delay: Duration, restarting_grace: Duration) -> Result<()> {
+    let container_name = &resolve_container_name(container_name).await?;
+    let max_attempts = max_attempts.max(1);
+    let mut attempt = 1;
+    
+    loop {
+        // Re-check the status each attempt, as a failed restart may have left it changed
+        let status = wait_while_restarting(container_name, restarting_grace).await?;
+        
+        let result = match status {
+            ContainerStatus::Running => {
+                info!("Restarting running container {}", container_name);
+                execute_docker_command(&["restart", container_name], "restart").await
+            },
+            ContainerStatus::Stopped => {
+                info!("Starting stopped container {}", container_name);
+                execute_docker_command(&["start", container_name], "start").await
+            },
+            ContainerStatus::Restarting => {
+                warn!("Container {} still restarting after {} seconds, restarting it", container_name, restarting_grace.as_secs());
+                execute_docker_command(&["restart", container_name], "restart").await
+            },
+            ContainerStatus::NotExists => {
+                return Err(anyhow!("Container {} does not exist and cannot be restarted", container_name));
+            }
+        };
+        
+        match result {
+            Ok(()) => break,
+            Err(e) if attempt < max_attempts => {
+                let delay = retry_delay * 2u32.pow(attempt - 1);
+                warn!("Attempt {}/{} to restart container {} failed: {}. Retrying in {} seconds", 
+                      attempt, max_attempts, container_name, e, delay.as_secs());
+                sleep(delay).await;
+                attempt += 1;
+            },
+            Err(e) => {
+                return Err(e.context(format!("Failed to restart container {} after {} attempts", 
+                                             container_name, max_attempts)));
+            }
         }
     }
     
@@ -70,27 +232,102 @@ pub async fn restart_container(container_name: &str) -> Result<()> {
     Ok(())
 }
 
-/// Get logs from a Docker container
-pub async fn get_container_logs(container_name: &str, tail_lines: u32) -> Result<String> {
-    let output = Command::new("docker")
-        .args(["logs", "--tail", &tail_lines.to_string(), container_name])
+/// Get logs from a Docker container, optionally limited to entries after `since`
+pub async fn get_container_logs(
+    container_name: &str,
+    tail_lines: u32,
+    since: Option<DateTime<Utc>>,
+    streams: LogStreams,
+) -> Result<ContainerLogs> {
+    let container_name = &resolve_container_name(container_name).await?;
+    let mut cmd = container_command();
+    cmd.args(["logs", "--tail", &tail_lines.to_string()]);
+    
+    if let Some(since) = since {
+        cmd.args(["--since", &since.to_rfc3339()]);
+    }
+    
+    // docker logs replays the container's stdout and stderr on its own, so an unwanted stream is just dropped
+    if !streams.stdout() {
+        cmd.stdout(Stdio::null());
+    }
+    if !streams.stderr() {
+        cmd.stderr(Stdio::null());
+    }
+    
+    let output = cmd.arg(container_name)
         .output()
         .await
         .context(format!("Failed to get logs for container {}", container_name))?;
     
-    let logs = String::from_utf8(output.stdout)
-        .context("Failed to parse container logs as UTF-8")?;
+    // Containers may log binary or mis-encoded data; replace invalid bytes rather than failing
+    Ok(ContainerLogs {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+/// Get the last lines of a file inside a running container
+pub async fn tail_container_file(container_name: &str, path: &str, tail_lines: u32) -> Result<String> {
+    let container_name = &resolve_container_name(container_name).await?;
+    
+    let output = container_command()
+        .args(["exec", container_name, "tail", "-n", &tail_lines.to_string(), path])
+        .output()
+        .await
+        .context(format!("Failed to execute docker exec command for container {}", container_name))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read {} in container {}: {}", path, container_name, stderr.trim()));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// CPU and memory usage of a running container
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ResourceUsage {
+    /// CPU usage in percent, where 100% is one full core
+    pub cpu_percent: f64,
+    /// Memory usage in percent of the container's limit
+    pub memory_percent: f64,
+}
+
+/// Get a snapshot of a container's CPU and memory usage
+pub async fn get_container_resource_usage(container_name: &str) -> Result<ResourceUsage> {
+    let container_name = &resolve_container_name(container_name).await?;
     
-    let stderr = String::from_utf8(output.stderr)
-        .context("Failed to parse container stderr logs as UTF-8")?;
+    let output = container_command()
+        .args(["stats", "--no-stream", "--format", "{{.CPUPerc}} {{.MemPerc}}", container_name])
+        .output()
+        .await
+        .context(format!("Failed to get resource usage for container {}", container_name))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("docker stats failed for container {}: {}", container_name, stderr.trim()));
+    }
     
-    // Combine stdout and stderr logs
-    Ok(format!("{}\n{}", logs, stderr))
+    parse_resource_usage(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse a `{{.CPUPerc}} {{.MemPerc}}` line from docker stats, e.g. "12.34% 56.78%"
+fn parse_resource_usage(line: &str) -> Result<ResourceUsage> {
+    let percentages: Vec<f64> = line.split_whitespace()
+        .map(|value| value.trim_end_matches('%').parse::<f64>())
+        .collect::<Result<_, _>>()
+        .context(format!("Unexpected docker stats output: {}", line.trim()))?;
+    
+    match percentages[..] {
+        [cpu_percent, memory_percent] => Ok(ResourceUsage { cpu_percent, memory_percent }),
+        _ => Err(anyhow!("Unexpected docker stats output: {}", line.trim())),
+    }
 }
 
 /// Execute a Docker command and handle errors
 async fn execute_docker_command(args: &[&str], operation: &str) -> Result<()> {
-    let status = Command::new("docker")
+    let status = container_command()
         .args(args)
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -109,43 +346,44 @@ async fn execute_docker_command(args: &[&str], operation: &str) -> Result<()> {
 /// Configuration for Docker Compose operations
 pub struct DockerComposeConfig {
     pub compose_dir: PathBuf,
-    pub compose_file: Option<String>,
+    /// Compose files in override order (later files override earlier ones)
+    pub compose_files: Vec<String>,
+    pub compose_profiles: Vec<String>,
+    /// Env file passed with `--env-file`, relative to the compose directory
+    pub env_file: Option<PathBuf>,
+    /// Longest to wait for the container to be running (and healthy) after a restart
+    pub ready_timeout: Duration,
     pub service_name: String,
 }
 
-/// Detect which Docker Compose command to use (V2 or legacy)
+/// Detect which compose command to use: the runtime's `compose` subcommand, else its standalone binary
 pub async fn detect_docker_compose_command() -> (String, bool) {
-    let docker_compose_v2 = Command::new("docker")
+    let runtime = container_runtime();
+    let compose_subcommand = container_command()
         .args(["compose", "version"])
         .output()
-        .await;
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false);
     
-    if docker_compose_v2.is_ok() {
-        ("docker compose".to_string(), true)
+    if compose_subcommand {
+        (format!("{} compose", runtime.binary()), true)
     } else {
-        ("docker-compose".to_string(), false)
+        (runtime.compose_binary().to_string(), false)
     }
 }
 
 /// Restart a service using Docker Compose
 pub async fn restart_with_docker_compose(config: &DockerComposeConfig) -> Result<()> {
-    let (compose_cmd, _is_v2) = detect_docker_compose_command().await;
-    
-    // Check if compose file exists
-    let compose_file = get_compose_file_arg(config)?;
+    // Check if compose files exist
+    let compose_files = get_compose_files(config)?;
+    check_env_file(config)?;
     
     // Execute docker-compose restart
     info!("Restarting container {} with Docker Compose", config.service_name);
     
-    let restart_cmd = format!("cd {} && {} {} restart {}", 
-                            config.compose_dir.display(), 
-                            compose_cmd, 
-                            compose_file,
-                            config.service_name);
-    
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(&restart_cmd)
+    let status = compose_command(config, &compose_files).await
+        .args(["restart", &config.service_name])
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .status()
@@ -158,29 +396,21 @@ pub async fn restart_with_docker_compose(config: &DockerComposeConfig) -> Result
     
     info!("Container {} restarted successfully with Docker Compose", config.service_name);
     
-    // Wait for container to be fully up
-    sleep(Duration::from_secs(5)).await;
+    wait_for_container_ready(&config.service_name, config.ready_timeout).await;
     
     Ok(())
 }
 
 /// Recreate containers using Docker Compose (down, build, up)
 pub async fn recreate_with_docker_compose(config: &DockerComposeConfig) -> Result<()> {
-    let (compose_cmd, _is_v2) = detect_docker_compose_command().await;
-    
-    // Check if compose file exists
-    let compose_file = get_compose_file_arg(config)?;
+    // Check if compose files exist
+    let compose_files = get_compose_files(config)?;
+    check_env_file(config)?;
     
     // Execute docker-compose down
     info!("Stopping containers with Docker Compose");
-    let down_cmd = format!("cd {} && {} {} down", 
-                         config.compose_dir.display(), 
-                         compose_cmd, 
-                         compose_file);
-    
-    let down_status = Command::new("sh")
-        .arg("-c")
-        .arg(&down_cmd)
+    let down_status = compose_command(config, &compose_files).await
+        .arg("down")
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .status()
@@ -193,14 +423,8 @@ pub async fn recreate_with_docker_compose(config: &DockerComposeConfig) -> Resul
     
     // Execute docker-compose build
     info!("Building containers with Docker Compose");
-    let build_cmd = format!("cd {} && {} {} build", 
-                          config.compose_dir.display(), 
-                          compose_cmd, 
-                          compose_file);
-    
-    let build_status = Command::new("sh")
-        .arg("-c")
-        .arg(&build_cmd)
+    let build_status = compose_command(config, &compose_files).await
+        .arg("build")
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .status()
@@ -213,14 +437,8 @@ pub async fn recreate_with_docker_compose(config: &DockerComposeConfig) -> Resul
     
     // Execute docker-compose up
     info!("Starting containers with Docker Compose");
-    let up_cmd = format!("cd {} && {} {} up -d", 
-                       config.compose_dir.display(), 
-                       compose_cmd, 
-                       compose_file);
-    
-    let up_status = Command::new("sh")
-        .arg("-c")
-        .arg(&up_cmd)
+    let up_status = compose_command(config, &compose_files).await
+        .args(["up", "-d"])
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .status()
@@ -232,20 +450,147 @@ pub async fn recreate_with_docker_compose(config: &DockerComposeConfig) -> Resul
     }
     
     info!("Containers recreated successfully with Docker Compose");
+    forget_resolved_names();
     
-    // Wait for containers to be fully up
-    sleep(Duration::from_secs(5)).await;
+    wait_for_container_ready(&config.service_name, config.ready_timeout).await;
     
     Ok(())
 }
 
-/// Get the compose file argument, checking for file existence
-fn get_compose_file_arg(config: &DockerComposeConfig) -> Result<String> {
-    if let Some(file) = &config.compose_file {
-        let file_path = config.compose_dir.join(file);
-        if file_path.exists() {
-            return Ok(format!("-f {}", file));
+/// Interval between container status polls while waiting for a restarted container
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Poll until a container is running and, if it defines a healthcheck, healthy
+///
+/// Gives up with a warning after `ready_timeout`; a readiness command can still catch a container that never comes up.
+pub async fn wait_for_container_ready(container_name: &str, ready_timeout: Duration) {
+    let deadline = tokio::time::Instant::now() + ready_timeout;
+    
+    loop {
+        let ready = match check_container_status(container_name).await {
+            Ok(ContainerStatus::Running) => match get_container_health(container_name).await {
+                Ok(Some(health)) => health == "healthy",
+                Ok(None) => true,
+                Err(e) => {
+                    debug!("Failed to read health of container {}: {}", container_name, e);
+                    true
+                }
+            },
+            Ok(_) => false,
+            Err(e) => {
+                debug!("Failed to check status of container {}: {}", container_name, e);
+                false
+            }
+        };
+        
+        if ready {
+            debug!("Container {} is ready", container_name);
+            return;
+        }
+        
+        if tokio::time::Instant::now() + READY_POLL_INTERVAL > deadline {
+            warn!("Container {} not ready after {} seconds", container_name, ready_timeout.as_secs());
+            return;
+        }
+        
+        sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+/// Get the names of the networks a container is attached to
+pub async fn get_container_networks(container_name: &str) -> Result<Vec<String>> {
+    let container_name = &resolve_container_name(container_name).await?;
+    
+    let output = container_command()
+        .args(["inspect", "--format", "{{range $name, $_ := .NetworkSettings.Networks}}{{$name}}\n{{end}}", container_name])
+        .output()
+        .await
+        .context("Failed to execute docker inspect command")?;
+    
+    if !output.status.success() {
+        return Err(anyhow!("Docker inspect failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Get the healthcheck status of a container (`starting`, `healthy`, `unhealthy`), or None without a healthcheck
+pub async fn get_container_health(container_name: &str) -> Result<Option<String>> {
+    let container_name = &resolve_container_name(container_name).await?;
+    
+    let output = container_command()
+        .args(["inspect", "--format", "{{if .State.Health}}{{.State.Health.Status}}{{end}}", container_name])
+        .output()
+        .await
+        .context("Failed to execute docker inspect command")?;
+    
+    if !output.status.success() {
+        return Err(anyhow!("Docker inspect failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    let health = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!health.is_empty()).then_some(health))
+}
+
+/// Build a compose command running in the compose directory, with the file and profile
+/// arguments passed as discrete args so paths with spaces or shell characters are safe
+async fn compose_command(config: &DockerComposeConfig, compose_files: &[String]) -> Command {
+    let (_compose_cmd, is_v2) = detect_docker_compose_command().await;
+    
+    let mut command = if is_v2 {
+        let mut command = container_command();
+        command.arg("compose");
+        command
+    } else {
+        Command::new(container_runtime().compose_binary())
+    };
+    
+    command.current_dir(&config.compose_dir);
+    
+    for file in compose_files {
+        command.args(["-f", file]);
+    }
+    
+    for profile in &config.compose_profiles {
+        command.args(["--profile", profile]);
+    }
+    
+    if let Some(env_file) = &config.env_file {
+        command.arg("--env-file").arg(env_file);
+    }
+    
+    command
+}
+
+/// Check that the configured env file exists, as compose would otherwise fail with a less clear error
+fn check_env_file(config: &DockerComposeConfig) -> Result<()> {
+    match &config.env_file {
+        Some(env_file) if !config.compose_dir.join(env_file).is_file() => {
+            Err(anyhow!("Compose env file not found: {}", config.compose_dir.join(env_file).display()))
         }
+        _ => Ok(()),
+    }
+}
+
+/// Get the compose files to use, checking that each exists
+fn get_compose_files(config: &DockerComposeConfig) -> Result<Vec<String>> {
+    let missing: Vec<&String> = config.compose_files.iter()
+        .filter(|file| !config.compose_dir.join(file).exists())
+        .collect();
+    
+    if !config.compose_files.is_empty() && missing.is_empty() {
+        return Ok(config.compose_files.clone());
+    }
+    
+    // A stack of override files must be complete, a single file may fall back to the defaults
+    if config.compose_files.len() > 1 {
+        let missing: Vec<&str> = missing.iter().map(|file| file.as_str()).collect();
+        return Err(anyhow!("Compose files not found in {}: {}", 
+                           config.compose_dir.display(), missing.join(", ")));
     }
     
     // Check for default files
@@ -253,11 +598,36 @@ fn get_compose_file_arg(config: &DockerComposeConfig) -> Result<String> {
     let compose_yml_path = config.compose_dir.join("compose.yml");
     
     if compose_file_path.exists() {
-        Ok("-f docker-compose.yml".to_string())
+        Ok(vec!["docker-compose.yml".to_string()])
     } else if compose_yml_path.exists() {
-        Ok("-f compose.yml".to_string())
+        Ok(vec!["compose.yml".to_string()])
     } else {
         Err(anyhow!("No docker-compose.yml or compose.yml file found in {}", 
                    config.compose_dir.display()))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_stale_pattern_match_is_forgotten() {
+        RESOLVED_NAMES.lock().unwrap()
+            .insert("stale-test-web-*".to_string(), "stale-test-web-1".to_string());
+        assert_eq!(resolve_container_name("stale-test-web-*").await.unwrap(), "stale-test-web-1");
+        
+        assert!(forget_resolved_name("stale-test-web-*"));
+        assert!(!forget_resolved_name("stale-test-web-*"));
+    }
+    
+    #[test]
+    fn test_parse_resource_usage() {
+        let usage = parse_resource_usage("153.20% 12.5%\n").unwrap();
+        assert_eq!(usage, ResourceUsage { cpu_percent: 153.2, memory_percent: 12.5 });
+        
+        // Stopped containers report "--" for both values
+        assert!(parse_resource_usage("-- --").is_err());
+        assert!(parse_resource_usage("").is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Errors that callers need to tell apart from generic failures
+#[derive(Debug)]
+pub enum WatcherError {
+    /// The git remote rejected our credentials (expired token, revoked deploy key, ...)
+    GitAuth {
+        operation: String,
+        message: String,
+    },
+    /// The remote has no commits on the branch yet (e.g. a freshly created repository)
+    NoCommits {
+        operation: String,
+        message: String,
+    },
+    /// A git command did not finish within the configured timeout and was killed
+    GitTimeout {
+        operation: String,
+        timeout: Duration,
+    },
+    /// An update was rejected by the service's validation and reverted
+    ValidationFailed {
+        service: String,
+    },
+}
+
+impl fmt::Display for WatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatcherError::GitAuth { operation, message } => {
+                write!(f, "Git {} failed: authentication rejected by remote: {}", operation, message)
+            }
+            WatcherError::NoCommits { operation, message } => {
+                write!(f, "Git {} failed: the branch has no commits yet: {}", operation, message)
+            }
+            WatcherError::GitTimeout { operation, timeout } => {
+                write!(f, "Git {} timed out after {} seconds and was killed", operation, timeout.as_secs())
+            }
+            WatcherError::ValidationFailed { service } => {
+                write!(f, "Validation failed for service {}", service)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WatcherError {}
\ No newline at end of file
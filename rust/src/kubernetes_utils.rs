@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::Duration;
+
+use crate::docker_utils::ContainerStatus;
+
+/// Check the rollout status of a Kubernetes deployment
+pub async fn check_deployment_status(namespace: &str, deployment: &str) -> Result<ContainerStatus> {
+    let target = format!("deployment/{}", deployment);
+    
+    let output = Command::new("kubectl")
+        .args(["rollout", "status", &target, "-n", namespace, "--watch=false"])
+        .output()
+        .await
+        .context("Failed to execute kubectl rollout status command")?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    
+    if output.status.success() && stdout.contains("successfully rolled out") {
+        debug!("Deployment {} in namespace {} is rolled out", deployment, namespace);
+        return Ok(ContainerStatus::Running);
+    }
+    
+    if stderr.contains("NotFound") || stderr.contains("not found") {
+        debug!("Deployment {} does not exist in namespace {}", deployment, namespace);
+        return Ok(ContainerStatus::NotExists);
+    }
+    
+    if !output.status.success() {
+        return Err(anyhow!("kubectl rollout status for {} failed: {}", target, stderr.trim()));
+    }
+    
+    warn!("Deployment {} exists but is not fully rolled out", deployment);
+    Ok(ContainerStatus::Stopped)
+}
+
+/// Restart a Kubernetes deployment and wait for the rollout to complete
+pub async fn rollout_restart(namespace: &str, deployment: &str, timeout: Duration) -> Result<()> {
+    let target = format!("deployment/{}", deployment);
+    
+    info!("Restarting {} in namespace {}", target, namespace);
+    
+    let status = Command::new("kubectl")
+        .args(["rollout", "restart", &target, "-n", namespace])
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .context("Failed to execute kubectl rollout restart command")?;
+    
+    if !status.success() {
+        return Err(anyhow!("kubectl rollout restart command failed with exit code: {:?}", status.code()));
+    }
+    
+    // Wait for the new pods to become ready
+    let timeout_arg = format!("--timeout={}s", timeout.as_secs());
+    let status = Command::new("kubectl")
+        .args(["rollout", "status", &target, "-n", namespace, &timeout_arg])
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .context("Failed to execute kubectl rollout status command")?;
+    
+    if !status.success() {
+        return Err(anyhow!("Rollout of {} did not complete within {} seconds", target, timeout.as_secs()));
+    }
+    
+    info!("Deployment {} rolled out successfully", deployment);
+    Ok(())
+}
\ No newline at end of file
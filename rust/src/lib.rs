@@ -1,14 +1,33 @@
+mod apache;
 mod config;
+mod custom_service;
+mod dependencies;
 mod docker_utils;
+mod error;
 mod git;
+mod kubernetes_utils;
+mod logger;
 mod nginx;
+mod notifier;
 mod service;
+mod stats;
+mod status_file;
 mod utils;
+mod webhook;
 
 // Re-export main components for easier access
-pub use config::{Config, ServiceConfig, GlobalSettings, ServiceType};
-pub use docker_utils::ContainerStatus;
-pub use git::{GitRepo, service as git_service};
-pub use nginx::{check_nginx_status, restart_nginx, check_nginx_logs};
-pub use service::{run_validation, restart_service, check_service_status};
-pub use utils::fix_permissions;
\ No newline at end of file
+pub use apache::ApacheService;
+pub use config::{Config, ConfigFormat, ServiceConfig, GlobalSettings, ServiceType, ConflictStrategy, CustomHandler, ExtraRepo, ContainerRuntime, LocalChangesPolicy, LogFile, LogStreams, NotifierConfig};
+pub use custom_service::CustomCommandService;
+pub use dependencies::{begin_cycle, wait_for_dependencies, CycleGuard};
+pub use docker_utils::{container_command, set_container_runtime, ContainerStatus, ResourceUsage};
+pub use error::WatcherError;
+pub use git::{GitRepo, RepoState, is_auth_error, is_no_commits_error, is_timeout_error, service as git_service};
+pub use logger::{init as init_logger, rate_limit as rate_limit_notification, ServiceLogger, HealthcheckClient};
+pub use nginx::{check_nginx_status, restart_nginx, check_nginx_logs, check_cert_expiry, check_server_conflicts, NginxService};
+pub use notifier::{dispatch as dispatch_event, notifiers_from_config, set_notifiers, EventKind, GenericWebhookNotifier, HealthcheckNotifier, LogNotifier, Notifier, SlackWebhookNotifier, WatcherEvent};
+pub use service::{check_expected_networks, check_log_files, ensure_service_ready, check_resource_usage, clear_validation_failure, is_validation_error, ensure_startable, record_validation_failure, run_staged_validation, run_validation, restart_service, wait_for_health_url, wait_for_readiness_command, check_service_status, check_health, run_pre_apply_hook, run_post_apply_hook, create_service_handler, GenericService, ServiceHandler, ServiceStatus};
+pub use stats::{counters as service_counters, log_summary, summary, ServiceCounters};
+pub use status_file::{forget as forget_service_status, record as record_service_status, record_failure as record_status_failure, writable as status_file_writable, CycleOutcome, ServiceState};
+pub use utils::{set_run_as, unprivileged_command, RunAs, check_containers_exist, check_dependencies, ensure_safe_permission_path, find_in_path, fix_permissions, print_dependency_report};
+pub use webhook::{serve as serve_webhook, register_service as register_webhook_service, unregister_service as unregister_webhook_service, trigger_service};
\ No newline at end of file
@@ -1,39 +1,42 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::Local;
 use env_logger::{Builder, Env};
-use log::{LevelFilter, Record, Level};
-use std::io::Write;
-use std::sync::Arc;
-use tokio::time::Duration;
+use log::Level;
+use std::io::{IsTerminal, Write};
+use std::sync::{LazyLock, Mutex};
+use tokio::time::{Duration, Instant};
 use std::collections::HashMap;
 
+use crate::config::LogFormat;
+
 // Log target for service-specific logs
 pub const SERVICE_LOG_TARGET: &str = "service";
 
 // Initialize logger with customizable options
-pub fn init(verbose: bool, log_file: Option<&str>) -> Result<()> {
+pub fn init(verbose: bool, log_file: Option<&str>, format: LogFormat) -> Result<()> {
     let env = Env::default()
         .filter_or("RUST_LOG", if verbose { "debug" } else { "info" });
     
     let mut builder = Builder::from_env(env);
+    let color = use_color(log_file);
     
     // Custom format that includes timestamp, log level, and optional service name
-    builder.format(|buf, record| {
+    builder.format(move |buf, record| {
+        // JSON mode emits one uncolored object per line for log pipelines
+        if format == LogFormat::Json {
+            return writeln!(buf, "{}", json_line(record));
+        }
+        
+        let service = record_service(record);
         let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
         
-        // Extract service name if available
-        let service_info = if record.target() == SERVICE_LOG_TARGET {
-            if let Some(service) = record.key_values().get("service".into()) {
-                format!("[{}] ", service)
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
-        };
+        let service_info = service
+            .map(|service| format!("[{}] ", service))
+            .unwrap_or_default();
         
         // Colorize output based on level
         let level_str = match record.level() {
+            level if !color => level.to_string(),
             Level::Error => format!("\x1B[31m{}\x1B[0m", record.level()), // Red
             Level::Warn => format!("\x1B[33m{}\x1B[0m", record.level()),  // Yellow
             Level::Info => format!("\x1B[32m{}\x1B[0m", record.level()),  // Green
@@ -69,6 +72,29 @@ pub fn init(verbose: bool, log_file: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+// Service a record is about, from its `service` key-value
+fn record_service(record: &log::Record) -> Option<String> {
+    record.key_values().get("service".into()).map(|service| service.to_string())
+}
+
+// A record as a JSON object with `timestamp`, `level`, `service`, `target` and `message`
+fn json_line(record: &log::Record) -> serde_json::Value {
+    serde_json::json!({
+        "timestamp": Local::now().to_rfc3339(),
+        "level": record.level().to_string(),
+        "service": record_service(record),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+}
+
+// Whether log levels are colored: only on a terminal, never in a log file, and not when NO_COLOR is set
+fn use_color(log_file: Option<&str>) -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    
+    log_file.is_none() && !no_color && std::io::stderr().is_terminal()
+}
+
 // Logger struct for service-specific logging
 #[derive(Clone)]
 pub struct ServiceLogger {
@@ -103,22 +129,37 @@ impl ServiceLogger {
     }
 }
 
+// Attempts made to deliver a healthcheck ping unless configured otherwise
+pub const DEFAULT_HEALTHCHECK_ATTEMPTS: u32 = 3;
+
+// Delay before the first retry of a healthcheck ping, doubled for each further retry
+const HEALTHCHECK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 // Healthcheck client for notifying multiple healthcheck endpoints
 pub struct HealthcheckClient {
     client: reqwest::Client,
     endpoints: HashMap<String, String>,
     timeout: Duration,
+    attempts: u32,
 }
 
 impl HealthcheckClient {
-    pub fn new(timeout_secs: u64) -> Self {
+    // Create a client whose ping attempts each time out after `timeout`
+    pub fn new(timeout: Duration) -> Self {
         Self {
             client: reqwest::Client::new(),
             endpoints: HashMap::new(),
-            timeout: Duration::from_secs(timeout_secs),
+            timeout,
+            attempts: DEFAULT_HEALTHCHECK_ATTEMPTS,
         }
     }
     
+    // Set how many times a ping is tried before it counts as failed
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+    
     pub fn add_service(&mut self, service_name: &str, url: &str) {
         if !url.is_empty() {
             self.endpoints.insert(service_name.to_string(), url.to_string());
@@ -132,68 +173,159 @@ impl HealthcheckClient {
             }
             
             let endpoint = if is_error {
-                format!("{}/fail", url)
+                fail_endpoint(url)
             } else {
                 url.to_string()
             };
             
-            self.client.post(&endpoint)
+            let request = self.client.post(&endpoint)
                 .body(message.to_string())
-                .timeout(self.timeout)
-                .send()
-                .await
+                .timeout(self.timeout);
+            send_with_retry(request, self.attempts).await
                 .context(format!("Failed to notify healthcheck for service {}", service_name))?;
         }
         
         Ok(())
     }
+}
+
+/// Last notification of each kind per service, for rate limiting
+static RECENT_NOTIFICATIONS: LazyLock<Mutex<HashMap<(String, String), RecentNotification>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// When a notification was last sent and how many were suppressed since
+struct RecentNotification {
+    sent_at: Instant,
+    suppressed: u32,
+}
+
+/// Decide whether a notification of a kind may be sent for a service, allowing one per window
+///
+/// Returns None when it is suppressed, otherwise the number suppressed since the last one sent.
+pub fn rate_limit(service_name: &str, kind: &str, window: Duration) -> Result<Option<u32>> {
+    let mut recent = RECENT_NOTIFICATIONS.lock()
+        .map_err(|_| anyhow::anyhow!("Notification state lock poisoned"))?;
+    let key = (service_name.to_string(), kind.to_string());
     
-    pub async fn notify_all(&self, message: &str, is_error: bool) -> Result<()> {
-        for (service_name, url) in &self.endpoints {
-            if url.is_empty() {
-                continue;
-            }
-            
-            let endpoint = if is_error {
-                format!("{}/fail", url)
-            } else {
-                url.to_string()
-            };
-            
-            // Use try_join_all or similar to do these concurrently if needed
-            match self.client.post(&endpoint)
-                .body(message.to_string())
-                .timeout(self.timeout)
-                .send()
-                .await {
-                    Ok(_) => log::debug!("Successfully notified healthcheck for {}", service_name),
-                    Err(e) => log::warn!("Failed to notify healthcheck for {}: {}", service_name, e),
-                }
+    match recent.get_mut(&key) {
+        Some(last) if last.sent_at.elapsed() < window => {
+            last.suppressed += 1;
+            log::debug!("Suppressed {} notification for {} ({} in the current window)", 
+                        kind, service_name, last.suppressed);
+            Ok(None)
+        }
+        last => {
+            let suppressed = last.map_or(0, |last| last.suppressed);
+            recent.insert(key, RecentNotification { sent_at: Instant::now(), suppressed: 0 });
+            Ok(Some(suppressed))
         }
-        
-        Ok(())
     }
 }
 
-// Simple function for backward compatibility
-pub async fn notify_healthcheck(url: &str, message: &str, is_error: bool) -> Result<()> {
-    if url.is_empty() {
-        return Ok(());
+// Failure endpoint of a healthcheck URL, keeping any query string after the added `/fail`
+fn fail_endpoint(url: &str) -> String {
+    let Ok(mut endpoint) = url::Url::parse(url) else {
+        return format!("{}/fail", url);
+    };
+    
+    if let Ok(mut segments) = endpoint.path_segments_mut() {
+        segments.pop_if_empty().push("fail");
     }
     
-    let client = reqwest::Client::new();
-    let endpoint = if is_error {
-        format!("{}/fail", url)
-    } else {
-        url.to_string()
-    };
+    endpoint.to_string()
+}
+
+// Send a healthcheck request, retrying failures and error statuses with backoff
+//
+// Only the last failure is returned; earlier ones are logged at debug level, so a brief
+// network blip does not look like a missed heartbeat.
+pub async fn send_with_retry(request: reqwest::RequestBuilder, attempts: u32) -> Result<()> {
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
     
-    client.post(&endpoint)
-        .body(message.to_string())
-        .timeout(Duration::from_secs(10))
-        .send()
-        .await
-        .context("Failed to notify healthcheck")?;
+    loop {
+        let this_attempt = request.try_clone()
+            .context("Healthcheck request cannot be retried")?;
+        let result = match this_attempt.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(anyhow!("Healthcheck endpoint returned status {}", response.status())),
+            Err(e) => Err(anyhow::Error::from(e)),
+        };
+        
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < attempts => {
+                let delay = HEALTHCHECK_RETRY_DELAY * 2u32.pow(attempt - 1);
+                log::debug!("Healthcheck attempt {}/{} failed: {}. Retrying in {} seconds", 
+                            attempt, attempts, e, delay.as_secs());
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.context(format!("Healthcheck failed after {} attempt(s)", attempts))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn test_json_line() {
+        let kvs = [("service", "web")];
+        let line = json_line(&log::Record::builder()
+            .args(format_args!("Restarted"))
+            .level(Level::Warn)
+            .target(SERVICE_LOG_TARGET)
+            .key_values(&kvs)
+            .build());
+        
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["service"], "web");
+        assert_eq!(line["target"], "service");
+        assert_eq!(line["message"], "Restarted");
+        assert!(line["timestamp"].is_string());
+        
+        // Plain log macros carry the service the same way
+        let line = json_line(&log::Record::builder()
+            .args(format_args!("Validation failed"))
+            .target("watcher")
+            .key_values(&kvs)
+            .build());
+        assert_eq!(line["service"], "web");
+        assert_eq!(line["target"], "watcher");
+        
+        let line = json_line(&log::Record::builder()
+            .args(format_args!("Starting"))
+            .target("watcher")
+            .build());
+        assert!(line["service"].is_null());
+    }
+    
+    #[test]
+    fn test_fail_endpoint() {
+        assert_eq!(fail_endpoint("https://hc.example.com/ping/abc"), "https://hc.example.com/ping/abc/fail");
+        assert_eq!(fail_endpoint("https://hc.example.com/ping/abc/"), "https://hc.example.com/ping/abc/fail");
+        // An existing query string stays after the added path segment
+        assert_eq!(fail_endpoint("https://hc.example.com/ping/abc?foo=bar"), "https://hc.example.com/ping/abc/fail?foo=bar");
+        assert_eq!(fail_endpoint("http://[2001:db8::1]:8080/ping?foo=bar&rid=1"), "http://[2001:db8::1]:8080/ping/fail?foo=bar&rid=1");
+    }
+    
+    #[tokio::test]
+    async fn test_healthcheck_client_times_out() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/ping", listener.local_addr().unwrap());
+        
+        let mut client = HealthcheckClient::new(Duration::from_millis(200)).with_attempts(1);
+        client.add_service("web", &url);
+        
+        let started = Instant::now();
+        assert!(client.notify("web", "down", true).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        
+        // Services without a healthcheck URL are skipped
+        assert!(client.notify("other", "down", true).await.is_ok());
+        drop(listener);
+    }
+}
@@ -1,39 +1,258 @@
 use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use clap::{Parser, Subcommand};
 use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex};
 use tokio::signal::ctrl_c;
-use tokio::sync::mpsc;
-use tokio::task::JoinSet;
-use tokio::time::{sleep, Duration};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::{sleep, timeout, timeout_at, Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
+mod apache;
 mod config;
+mod custom_service;
+mod dependencies;
 mod docker_utils;
+mod error;
 mod git;
+mod kubernetes_utils;
+mod logger;
 mod nginx;
+mod notifier;
 mod service;
+mod stats;
+mod status_file;
 mod utils;
+mod webhook;
 
-use config::{Config, GlobalSettings, ServiceConfig, ServiceType};
+use config::{Config, ConfigFormat, GlobalSettings, LogFormat, ServiceConfig, ServiceType, STDIN_CONFIG};
 use docker_utils::ContainerStatus;
+use docker_utils::set_container_runtime;
 use git::service as git_service;
-use nginx::{check_nginx_logs, restart_nginx};
-use service::{check_service_status, restart_service, run_validation};
-use utils::fix_permissions;
+use logger::ServiceLogger;
+use nginx::{check_cert_expiry, check_nginx_logs, check_server_conflicts, restart_nginx};
+use notifier::{EventKind, WatcherEvent};
+use error::WatcherError;
+use service::{check_expected_networks, check_service_status, check_log_files, ensure_service_ready, check_resource_usage, clear_validation_failure, create_service_handler, ensure_startable, is_validation_error, record_validation_failure, restart_service, run_post_apply_hook, run_pre_apply_hook, run_staged_validation, run_validation, wait_for_health_url, wait_for_readiness_command, ServiceStatus};
+use status_file::{CycleOutcome, ServiceState};
+use utils::{check_containers_exist, check_dependencies, fix_permissions, print_dependency_report, set_run_as};
+
+/// Version string including the commit and time the binary was built from
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ", env!("WATCHER_GIT_SHA"),
+    "\nbuilt:  ", env!("WATCHER_BUILD_TIMESTAMP"),
+);
+
+/// How often the supervisor looks for services whose checks have stalled
+const STALENESS_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time of the last successful check of each monitored service
+static LAST_CHECKS: LazyLock<Mutex<HashMap<String, CheckRecord>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Progress of a service's monitoring task, as seen by the staleness supervisor
+struct CheckRecord {
+    last_success: Instant,
+    interval: Duration,
+    notification_window: Duration,
+    stale: bool,
+}
+
+/// Consecutive failed checks of a service
+#[derive(Default)]
+struct FailureStreak {
+    failures: u32,
+    since: Option<Instant>,
+}
+
+impl FailureStreak {
+    /// Record a failed check
+    fn record_failure(&mut self) {
+        self.failures += 1;
+        self.since.get_or_insert_with(Instant::now);
+    }
+    
+    /// Record a successful check, returning the length and duration of the streak it ended
+    fn record_success(&mut self) -> Option<(u32, Duration)> {
+        let since = self.since.take()?;
+        Some((std::mem::take(&mut self.failures), since.elapsed()))
+    }
+    
+    /// Delay before the next check: the interval doubled for every failure up to `max`,
+    /// plus up to 10% jitter so services failing on the same remote spread out
+    fn backoff(&self, interval: Duration, max: Duration) -> Duration {
+        if self.failures == 0 {
+            return interval;
+        }
+        
+        let delay = interval.saturating_mul(1 << self.failures.min(16)).min(max.max(interval));
+        let jitter_nanos = (delay / 10).as_nanos() as u64;
+        if jitter_nanos == 0 {
+            return delay;
+        }
+        
+        // RandomState is seeded randomly, which is all the randomness jitter needs
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(self.failures);
+        delay + Duration::from_nanos(hasher.finish() % jitter_nanos)
+    }
+}
+
+/// Consecutive validation failures of a service, pausing its updates once they reach the threshold
+#[derive(Default)]
+struct CircuitBreaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Time left before the next probe while the circuit is open
+    fn remaining_cooldown(&self) -> Option<Duration> {
+        self.open_until.and_then(|until| until.checked_duration_since(Instant::now()))
+    }
+    
+    /// Whether the cooldown has passed and the next check probes for a valid update
+    fn probing(&self) -> bool {
+        self.open_until.is_some() && self.remaining_cooldown().is_none()
+    }
+    
+    /// Record a validation failure, returning true if it opened the circuit
+    fn record_failure(&mut self, threshold: u32, cooldown: Duration) -> bool {
+        self.failures += 1;
+        if self.failures < threshold {
+            return false;
+        }
+        
+        self.open_until = Some(Instant::now() + cooldown);
+        true
+    }
+    
+    /// Record an update that passed validation, returning true if it closed the circuit
+    fn record_success(&mut self) -> bool {
+        self.failures = 0;
+        self.open_until.take().is_some()
+    }
+}
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "watcher", version, long_version = LONG_VERSION, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    
+    /// Check every service immediately instead of waiting for the startup grace period
+    #[arg(long)]
+    no_grace: bool,
+    
+    /// Print the versions of git, docker, docker compose and the SSH agent status, then exit
+    #[arg(long)]
+    check_deps: bool,
+    
+    /// Services config file, overriding SERVICES_CONFIG and the default locations; `-` reads stdin
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+    
+    /// Format of a config without a file extension, such as one read from stdin (json, toml or yaml)
+    #[arg(long, value_name = "FORMAT")]
+    config_format: Option<ConfigFormat>,
+}
+
+impl Cli {
+    /// Load and validate the configuration, applying the command-line overrides
+    fn load_config(&self) -> Result<Config> {
+        let mut config = Config::load_from(self.config.as_deref(), self.config_format)?;
+        self.apply_overrides(&mut config.global_settings);
+        config.ensure_valid()?;
+        set_run_as(&config.global_settings)?;
+        set_container_runtime(&config.global_settings);
+        notifier::set_notifiers(notifier::notifiers_from_config(&config));
+        Ok(config)
+    }
+    
+    /// Whether the configuration is read from stdin, which can only be done once
+    fn config_from_stdin(&self) -> bool {
+        let path = self.config.clone().or_else(|| std::env::var("SERVICES_CONFIG").ok());
+        path.as_deref() == Some(STDIN_CONFIG)
+    }
+    
+    /// Apply command-line overrides on top of the loaded settings
+    fn apply_overrides(&self, global: &mut GlobalSettings) {
+        if self.no_grace {
+            global.skip_startup_grace = true;
+        }
+    }
+}
+
+/// Subcommands; without one the watcher runs as a service
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Show the current commit, container status and last modification time of each service
+    Status,
+    /// Convert the legacy environment variable configuration into a services file
+    Migrate {
+        /// Services file to write
+        #[arg(long, value_name = "PATH", default_value = "services.json")]
+        out: PathBuf,
+    },
+    /// Print the loaded configuration as JSON
+    Config {
+        /// Print the values that apply to each service after merging in the global defaults
+        #[arg(long)]
+        effective: bool,
+    },
+}
 
 /// Main entry point for the application
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging from environment
-    env_logger::init_from_env(
-        env_logger::Env::default().filter_or("RUST_LOG", "info")
-    );
-
+    // Parse command-line arguments (handles --help and --version)
+    let cli = Cli::parse();
+    
+    if cli.check_deps {
+        // Report the configured container runtime when a configuration can be read
+        if !cli.config_from_stdin() {
+            if let Ok(config) = Config::load_from(cli.config.as_deref(), cli.config_format) {
+                set_container_runtime(&config.global_settings);
+            }
+        }
+        print_dependency_report().await;
+        return Ok(());
+    }
+    
+    if let Some(Commands::Migrate { out }) = &cli.command {
+        logger::init(false, None, LogFormat::default())?;
+        let config = Config::load_legacy()?;
+        config.save(out)?;
+        info!("Wrote {} service(s) to {}", config.services.len(), out.display());
+        return Ok(());
+    }
+    
     // Load configuration
-    let config = match Config::load() {
+    let config = cli.load_config();
+    
+    // Initialize logging, using the configured options when available
+    let (verbose, log_file, log_format) = match &config {
+        Ok(cfg) => (
+            cfg.global_settings.verbose,
+            cfg.global_settings.log_file.clone(),
+            cfg.global_settings.log_format,
+        ),
+        Err(_) => (false, None, LogFormat::default()),
+    };
+    logger::init(verbose, log_file.as_deref(), log_format)?;
+    
+    let config = match config {
+        Ok(cfg) if cli.command.is_some() => cfg,
         Ok(cfg) => {
             cfg.display();
             cfg
@@ -43,7 +262,32 @@ async fn main() -> Result<()> {
             return Err(e);
         }
     };
-
+    
+    if let Some(Commands::Status) = cli.command {
+        for service in &config.services {
+            ServiceStatus::collect(service, &config.global_settings).await.display();
+        }
+        return Ok(());
+    }
+    
+    if let Some(Commands::Config { effective }) = cli.command {
+        if effective {
+            config.display_effective();
+        } else {
+            let content = serde_json::to_string_pretty(&config)
+                .context("Failed to serialize configuration")?;
+            println!("{}", content);
+        }
+        return Ok(());
+    }
+    
+    // Fail fast if binaries the services need are missing
+    if let Err(e) = check_dependencies(&config).await {
+        error!("{}", e);
+        return Err(e);
+    }
+    check_containers_exist(&config).await;
+    
     // Wrap in Arc for sharing between tasks
     let config = Arc::new(config);
     
@@ -59,147 +303,780 @@ async fn main() -> Result<()> {
     }) {
         warn!("Failed to write lockfile: {}", e);
     }
-
+    
     // Setup signal handler channel
     let (tx, mut rx) = mpsc::channel(1);
     let tx_clone = tx.clone();
-
-    // Handle Ctrl+C signal
+    
+    // Handle Ctrl+C and SIGTERM
     tokio::spawn(async move {
-        if let Err(e) = ctrl_c().await {
-            error!("Failed to listen for Ctrl+C: {}", e);
+        if let Err(e) = shutdown_signal().await {
+            error!("Failed to listen for shutdown signals: {}", e);
             return;
         }
         info!("Received shutdown signal. Stopping...");
         let _ = tx_clone.send(()).await;
     });
-
-    // Set up task set for monitoring services
+    
+    // Handle SIGHUP as a request to reload the configuration
+    let (reload_tx, mut reload_rx) = mpsc::channel(1);
+    spawn_reload_listener(reload_tx);
+    
+    // Start the webhook server so pushes trigger immediate checks
+    if let Some(port) = config.global_settings.webhook_port {
+        let secret = config.global_settings.webhook_secret.clone();
+        let provider = config.global_settings.webhook_provider;
+        tokio::spawn(async move {
+            if let Err(e) = webhook::serve(port, secret, provider).await {
+                error!("Webhook server stopped: {:#}", e);
+            }
+        });
+    }
+    
+    // Alert when a service's monitoring task stops completing checks
+    tokio::spawn(supervise_checks());
+    
+    // Set up task set for monitoring services, cancelled together at shutdown
+    let shutdown = CancellationToken::new();
     let mut tasks = JoinSet::new();
+    let mut running: HashMap<String, RunningService> = HashMap::new();
+    let mut global = config.global_settings.clone();
     
     // Create a task for each service
     for (idx, service) in config.services.iter().enumerate() {
-        let service_config = service.clone();
-        let global_config = config.global_settings.clone();
-        let tx = tx.clone();
-        
-        info!("Starting monitoring task for service: {}", service.name);
-        
-        tasks.spawn(async move {
-            monitor_service(service_config, global_config, idx, tx).await
-        });
+        let task = spawn_service_task(&mut tasks, service, &global, idx, &tx, &shutdown);
+        running.insert(service.name.clone(), task);
     }
-
-    // Wait for shutdown signal or task completion
-    tokio::select! {
-        _ = rx.recv() => {
-            info!("Shutdown signal received, stopping all tasks...");
-            tasks.abort_all();
-        }
-        res = tasks.join_next() => {
-            if let Some(result) = res {
+    
+    // Error of a failed service task, returned so the process exits non-zero
+    let mut task_error = None;
+    
+    // Wait for shutdown signal, reload requests or task completion
+    loop {
+        tokio::select! {
+            _ = rx.recv() => {
+                info!("Shutdown signal received, stopping all tasks...");
+                drain_tasks(&mut tasks, &shutdown, global.shutdown_timeout_duration()).await;
+                break;
+            }
+            Some(()) = reload_rx.recv() => {
+                reload_config(&cli, &mut tasks, &mut running, &mut global, &tx, &shutdown).await;
+            }
+            Some(result) = tasks.join_next_with_id() => {
+                // Tasks stopped by a config reload have been removed or replaced already
+                let id = match &result {
+                    Ok((id, _)) => *id,
+                    Err(e) => e.id(),
+                };
+                if !running.values().any(|current| current.handle.id() == id) {
+                    continue;
+                }
+                
                 match result {
-                    Ok(service_result) => {
+                    Ok((_, service_result)) => {
                         match service_result {
-                            Ok(name) => info!("Service task for '{}' completed", name),
-                            Err(e) => error!("Service task failed with error: {}", e),
+                            // Init-only services finish after their initial sync
+                            Ok(name) => {
+                                info!("Service task for '{}' completed", name);
+                                if !tasks.is_empty() {
+                                    continue;
+                                }
+                                info!("All service tasks have completed");
+                            }
+                            Err(e) => {
+                                error!("Service task failed with error: {}", e);
+                                task_error = Some(e);
+                            }
                         }
                     }
                     Err(e) => error!("Task join error: {}", e),
                 }
+                // If one task failed, trigger shutdown for all
+                drain_tasks(&mut tasks, &shutdown, global.shutdown_timeout_duration()).await;
+                break;
             }
-            // If one task ended, trigger shutdown for all
-            let _ = tx.send(()).await;
         }
     }
-
+    
     // Cleanup lockfile
     if lockfile.exists() {
         if let Err(e) = std::fs::remove_file(&lockfile) {
             warn!("Failed to remove lockfile: {}", e);
         }
     }
-
+    
+    stats::log_summary();
     info!("Config Watcher shutdown complete");
+    
+    match task_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Wait for Ctrl+C, or SIGTERM as sent by `docker stop`
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    
+    #[cfg(not(unix))]
+    ctrl_c().await
+}
+
+/// Let the service tasks finish their current cycle, aborting the ones still running after `grace`
+async fn drain_tasks(tasks: &mut JoinSet<Result<String>>, shutdown: &CancellationToken, grace: Duration) {
+    shutdown.cancel();
+    
+    let drained = timeout(grace, async {
+        while let Some(result) = tasks.join_next().await {
+            match result {
+                Ok(Err(e)) => error!("Service task failed with error: {}", e),
+                Err(e) if !e.is_cancelled() => error!("Task join error: {}", e),
+                _ => {}
+            }
+        }
+    }).await;
+    
+    if drained.is_err() {
+        warn!("{} service tasks still running after {} seconds, aborting them", tasks.len(), grace.as_secs());
+        tasks.abort_all();
+    }
+}
+
+/// A monitoring task together with the configuration it was started with
+struct RunningService {
+    config: ServiceConfig,
+    handle: AbortHandle,
+    /// Cancelled to let the task finish its current cycle and stop
+    token: CancellationToken,
+    /// Resolves once the task has ended, however it ended
+    done: oneshot::Receiver<()>,
+}
+
+/// Let stopped service tasks finish their current cycle, aborting the ones still running after `grace`
+async fn stop_tasks(stopping: Vec<RunningService>, grace: Duration) {
+    for task in &stopping {
+        task.token.cancel();
+    }
+    
+    let deadline = Instant::now() + grace;
+    for task in stopping {
+        if timeout_at(deadline, task.done).await.is_err() {
+            warn!(service = task.config.name; "Task still running after {} seconds, aborting it", grace.as_secs());
+            task.handle.abort();
+        }
+    }
+}
+
+/// Spawn the monitoring task for a single service
+fn spawn_service_task(
+    tasks: &mut JoinSet<Result<String>>,
+    service: &ServiceConfig,
+    global: &GlobalSettings,
+    idx: usize,
+    tx: &mpsc::Sender<()>,
+    shutdown: &CancellationToken,
+) -> RunningService {
+    let service_config = service.clone();
+    let global_config = global.clone();
+    let tx = tx.clone();
+    let token = shutdown.child_token();
+    let task_token = token.clone();
+    let (done_tx, done) = oneshot::channel();
+    let trigger = webhook::register_service(service, global);
+    
+    // The first check is not due before the grace period has passed
+    let first_check = Instant::now() + service.effective_startup_grace_period(global);
+    if let Ok(mut checks) = LAST_CHECKS.lock() {
+        checks.insert(service.name.clone(), CheckRecord {
+            last_success: first_check,
+            interval: Duration::from_secs(global.watch_interval),
+            notification_window: global.notification_window_duration(),
+            stale: false,
+        });
+    }
+    
+    info!("Starting monitoring task for service: {}", service.name);
+    
+    let handle = tasks.spawn(async move {
+        // Dropped when the task ends or is aborted, which resolves `done`
+        let _done = done_tx;
+        monitor_service(service_config, global_config, idx, tx, trigger, task_token).await
+    });
+    
+    RunningService { config: service.clone(), handle, token, done }
+}
+
+/// Forward SIGHUP signals to the main loop as reload requests
+fn spawn_reload_listener(reload_tx: mpsc::Sender<()>) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                error!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+        
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading configuration");
+            if reload_tx.send(()).await.is_err() {
+                break;
+            }
+        }
+    });
+    
+    #[cfg(not(unix))]
+    drop(reload_tx);
+}
+
+/// Reload the configuration and reconcile the running service tasks with it
+async fn reload_config(
+    cli: &Cli,
+    tasks: &mut JoinSet<Result<String>>,
+    running: &mut HashMap<String, RunningService>,
+    global: &mut GlobalSettings,
+    tx: &mpsc::Sender<()>,
+    shutdown: &CancellationToken,
+) {
+    if cli.config_from_stdin() {
+        warn!("Configuration was read from stdin and cannot be reloaded, keeping the current one");
+        return;
+    }
+    
+    // Keep the current configuration if the new one is broken
+    let config = match cli.load_config() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            error!("Failed to reload configuration, keeping the current one: {:#}", e);
+            return;
+        }
+    };
+    
+    // Global settings apply to every task, so a change restarts all of them
+    let global_changed = config.global_settings != *global;
+    if config.global_settings.webhook_port != global.webhook_port ||
+       config.global_settings.webhook_secret != global.webhook_secret ||
+       config.global_settings.webhook_provider != global.webhook_provider {
+        warn!("Webhook server settings changed; restart the watcher to apply them");
+    }
+    if global_changed {
+        info!("Global settings changed, restarting all service tasks");
+        *global = config.global_settings.clone();
+    }
+    
+    // Stop tasks for services that were removed or changed
+    let stale: Vec<String> = running.iter()
+        .filter(|(name, current)| match config.services.iter().find(|s| &s.name == *name) {
+            Some(service) => global_changed || *service != current.config,
+            None => true,
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    
+    let mut stopping = Vec::new();
+    let mut removed = Vec::new();
+    for name in stale {
+        if config.services.iter().any(|s| s.name == name) {
+            info!("Configuration for service '{}' changed, restarting its task", name);
+        } else {
+            info!("Service '{}' removed from configuration, stopping its task", name);
+            removed.push(name.clone());
+        }
+        stopping.extend(running.remove(&name));
+    }
+    
+    // The old task must have stopped before a new one works on the same checkout and container
+    stop_tasks(stopping, global.shutdown_timeout_duration()).await;
+    
+    // Forgotten only now, so a cycle finishing during the stop cannot record the service again
+    for name in &removed {
+        webhook::unregister_service(name);
+        forget_checks(name);
+        status_file::forget(name);
+    }
+    
+    // Start tasks for new and changed services
+    for (idx, service) in config.services.iter().enumerate() {
+        if !running.contains_key(&service.name) {
+            let task = spawn_service_task(tasks, service, global, idx, tx, shutdown);
+            running.insert(service.name.clone(), task);
+        }
+    }
+    
+    info!("Configuration reloaded ({} services)", running.len());
+}
+
+/// Record that a service completed a check
+fn record_check(name: &str) {
+    if let Ok(mut checks) = LAST_CHECKS.lock() {
+        if let Some(record) = checks.get_mut(name) {
+            if record.stale {
+                info!(service = name; "Checks have resumed");
+            }
+            record.last_success = Instant::now();
+            record.stale = false;
+        }
+    }
+}
+
+/// Stop supervising a service that is no longer monitored
+fn forget_checks(name: &str) {
+    if let Ok(mut checks) = LAST_CHECKS.lock() {
+        checks.remove(name);
+    }
+}
+
+/// Warn about services that have not completed a check in more than 3 intervals
+async fn supervise_checks() {
+    loop {
+        sleep(STALENESS_POLL_INTERVAL).await;
+        
+        // Collect alerts first so the lock is not held across the notifications
+        let stale: Vec<(String, Duration, Duration)> = match LAST_CHECKS.lock() {
+            Ok(mut checks) => checks.iter_mut()
+                .filter(|(_, record)| !record.stale && record.last_success.elapsed() > record.interval * 3)
+                .map(|(name, record)| {
+                    record.stale = true;
+                    (name.clone(), record.last_success.elapsed(), record.notification_window)
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+        
+        for (name, elapsed, window) in stale {
+            let message = format!("No successful check in {} seconds; the monitoring task may be stuck", elapsed.as_secs());
+            notifier::dispatch(WatcherEvent::new(&name, EventKind::Stale, message), window).await;
+        }
+    }
+}
+
+/// Announce that a service's checks succeed again after a failure streak
+async fn report_recovery(service: &ServiceConfig, global: &GlobalSettings, failures: u32, duration: Duration) {
+    let message = format!("Recovered after {} consecutive failed checks (failing for {} seconds)", 
+                          failures, duration.as_secs());
+    let event = WatcherEvent::new(&service.name, EventKind::Recovered, message);
+    notifier::dispatch(event, global.notification_window_duration()).await;
+}
+
+/// Report the repositories of a read-only service whose remote moved ahead of the checkout
+async fn report_drift(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    for (repo_url, local, remote) in git_service::check_drift(service, global).await? {
+        let message = format!("{} drifted: checkout is at {}, remote is at {}", repo_url, local, remote);
+        let event = WatcherEvent::new(&service.name, EventKind::Drift { repo_url, local, remote }, message);
+        notifier::dispatch(event, global.notification_window_duration()).await;
+    }
+    
     Ok(())
 }
 
+/// Report remote branches matching `notify_branches` whose tip moved since the last check
+async fn report_advanced_branches(service: &ServiceConfig, global: &GlobalSettings) {
+    let advanced = match git_service::advanced_branches(service, global).await {
+        Ok(advanced) => advanced,
+        Err(e) => {
+            warn!(service = service.name; "Failed to check notify_branches: {}", e);
+            return;
+        }
+    };
+    
+    for (branch, commit) in advanced {
+        let message = format!("Branch {} advanced to {}", branch, commit);
+        let event = WatcherEvent::new(&service.name, EventKind::BranchAdvanced { branch, commit }, message);
+        notifier::dispatch(event, global.notification_window_duration()).await;
+    }
+}
+
+/// Announce that a service stops pulling updates after repeated validation failures
+async fn report_circuit_open(service: &ServiceConfig, global: &GlobalSettings, failures: u32, cooldown: Duration) {
+    let message = format!("Circuit open after {} consecutive validation failures, pausing updates for {} seconds", 
+                          failures, cooldown.as_secs());
+    let event = WatcherEvent::new(&service.name, EventKind::CircuitOpen, message);
+    notifier::dispatch(event, global.notification_window_duration()).await;
+}
+
 /// Monitor a single service for changes
 async fn monitor_service(
     service: ServiceConfig, 
     global: GlobalSettings,
     idx: usize,
-    shutdown_tx: mpsc::Sender<()>
+    shutdown_tx: mpsc::Sender<()>,
+    trigger: Arc<Notify>,
+    shutdown: CancellationToken,
 ) -> Result<String> {
     let service_name = service.name.clone();
-    info!("Starting monitoring for service: {}", service_name);
+    let log = ServiceLogger::new(&service_name);
+    log.info("Starting monitoring for service");
+    
+    if let Some(commit) = &service.pinned_commit {
+        log.warn(&format!("Service is pinned to commit {}; new commits on the branch will be ignored", commit));
+    }
     
     // Startup grace period
-    let grace_period = parse_duration(&global.startup_grace_period)
-        .unwrap_or_else(|_| Duration::from_secs(30));
+    let grace_period = service.effective_startup_grace_period(&global);
     
-    info!("[{}] Waiting {} seconds for startup grace period", 
-          service_name, grace_period.as_secs());
-    sleep(grace_period).await;
+    if grace_period.is_zero() {
+        log.info("Skipping startup grace period");
+    } else {
+        log.info(&format!("Waiting {} seconds for startup grace period", grace_period.as_secs()));
+        tokio::select! {
+            _ = sleep(grace_period) => {}
+            _ = shutdown.cancelled() => return Ok(service_name),
+        }
+    }
     
-    // Ensure the repository is properly initialized
-    match git_service::init_repository(&service, &global).await {
-        Ok(_) => info!("[{}] Git repository initialized", service_name),
-        Err(e) => {
-            error!("[{}] Failed to initialize repository: {}", service_name, e);
-            return Err(e.into());
+    // Ensure the repository is properly initialized; read-only services leave the checkout to others
+    if service.read_only {
+        log.info("Read-only mode: reporting drift without pulling or restarting");
+    } else {
+        match git_service::init_repository(&service, &global).await {
+            Ok(_) => log.info("Git repository initialized"),
+            Err(e) => {
+                log.error(&format!("Failed to initialize repository: {}", e));
+                return Err(e);
+            }
         }
     }
     
+    // Init-only services are provisioned once and never pulled again
+    if !service.watch {
+        log.info("Service is in init-only mode, not watching for updates");
+        forget_checks(&service_name);
+        return Ok(service_name);
+    }
+    
     // Set watch interval
     let watch_interval = Duration::from_secs(global.watch_interval);
+    let cycle_timeout = global.cycle_timeout_duration();
+    
+    let mut streak = FailureStreak::default();
+    let mut breaker = CircuitBreaker::default();
+    let counters = stats::counters(&service_name);
     
-    // Main monitoring loop
+    // Main monitoring loop; a cycle that has started runs to completion, but no new one starts after shutdown
     loop {
-        info!("[{}] Checking for updates...", service_name);
+        if shutdown.is_cancelled() {
+            log.info("Shutting down, no further checks");
+            return Ok(service_name);
+        }
         
-        // Check for updates in the repository
-        match git_service::check_for_updates(&service, &global).await {
-            Ok(updated) => {
-                if updated {
-                    info!("[{}] Updates detected, applying changes", service_name);
-                    
-                    // Handle service-specific updates
-                    match service.service_type {
-                        ServiceType::Nginx => {
-                            handle_nginx_update(&service, &global, idx).await?;
-                        },
-                        ServiceType::Apache => {
-                            handle_apache_update(&service, &global).await?;
-                        },
-                        ServiceType::Generic | ServiceType::Custom(_) => {
-                            handle_generic_update(&service, &global).await?;
+        // Leave a service whose updates keep failing validation alone until the cooldown has passed
+        if let Some(remaining) = breaker.remaining_cooldown() {
+            log.debug(&format!("Circuit open, skipping update check ({} seconds of cooldown left)", remaining.as_secs()));
+            record_check(&service_name);
+            tokio::select! {
+                _ = sleep(watch_interval.min(remaining)) => {}
+                _ = shutdown.cancelled() => {}
+            }
+            continue;
+        }
+        
+        if breaker.probing() {
+            log.info("Circuit breaker cooldown over, probing for a valid update");
+        }
+        
+        let cycle = dependencies::begin_cycle(&service_name);
+        log.info("Checking for updates...");
+        
+        // Bound the whole cycle so a stuck step cannot push the loop off schedule
+        let cycle_result = timeout(cycle_timeout, async {
+            // Report new commits on branches that are watched but not deployed
+            report_advanced_branches(&service, &global).await;
+            
+            // Validate the remote tip in a staged checkout first, so a broken commit never reaches the live tree
+            let staged = if service.stage_before_apply && !service.read_only {
+                validate_staged_update(&service, &global).await
+            } else {
+                Ok(())
+            };
+            
+            // Check for updates in the repository
+            let check = match staged {
+                Err(e) if is_validation_error(&e) => return Err(e),
+                Err(e) => Err(e),
+                Ok(()) if service.read_only => report_drift(&service, &global).await.map(|_| false),
+                Ok(()) => git_service::check_for_updates(&service, &global).await,
+            };
+            
+            let outcome = match check {
+                Ok(updated) => {
+                    if updated {
+                        log.info("Updates detected, applying changes");
+                        
+                        // Restart only after the services this one depends on have applied their updates
+                        dependencies::wait_for_dependencies(&service).await;
+                        
+                        // Handle service-specific updates
+                        match service.service_type {
+                            ServiceType::Nginx => {
+                                handle_nginx_update(&service, &global, idx).await?;
+                            },
+                            ServiceType::Apache | ServiceType::Generic | ServiceType::Kubernetes | ServiceType::Custom(_) => {
+                                handle_generic_update(&service, &global).await?;
+                            }
                         }
-                    }
-                } else {
-                    info!("[{}] No updates detected", service_name);
-                    
-                    // Periodic checks even if no updates
-                    if service.service_type == ServiceType::Nginx && 
-                       service.effective_monitor_logs(global.monitor_logs) {
-                        // Create a simplified nginx config for the specific service
-                        if let Ok(nginx_config) = Config::make_nginx_config(&service, &global) {
-                            if let Err(e) = check_nginx_logs(&nginx_config).await {
-                                warn!("[{}] Error checking Nginx logs: {}", service_name, e);
+                        
+                        counters.record_update();
+                        if breaker.record_success() {
+                            log.info("Update passed validation, circuit closed");
+                        }
+                        
+                        if service.tag_deploys {
+                            if let Err(e) = git_service::tag_deploy(&service, &global).await {
+                                log.warn(&format!("Failed to tag deploy: {:#}", e));
                             }
                         }
+                    } else {
+                        log.info("No updates detected");
+                        
+                        // Periodic checks even if no updates
+                        if service.service_type == ServiceType::Nginx && 
+                           service.effective_monitor_logs(global.monitor_logs) {
+                            // Create a simplified nginx config for the specific service
+                            if let Ok(nginx_config) = Config::make_nginx_config(&service, &global) {
+                                if let Err(e) = check_nginx_logs(&nginx_config).await {
+                                    log.warn(&format!("Error checking Nginx logs: {}", e));
+                                }
+                            }
+                        }
+                        
+                        if service.service_type == ServiceType::Nginx {
+                            if let Err(e) = check_cert_expiry(&service, &global).await {
+                                log.warn(&format!("Error checking TLS certificates: {}", e));
+                            }
+                        }
+                        
+                        // Catch a change that made the container leak memory or spin the CPU
+                        if service.monitor_resources {
+                            match check_resource_usage(&service).await {
+                                Ok(warnings) => {
+                                    for line in warnings {
+                                        log.warn(&line);
+                                    }
+                                },
+                                Err(e) => log.warn(&format!("Error checking resource usage: {}", e)),
+                            }
+                        }
+                        
+                        // Scan the log files the service asked to have watched
+                        match check_log_files(&service, &global).await {
+                            Ok(problems) => {
+                                for line in problems {
+                                    log.warn(&line);
+                                }
+                            },
+                            Err(e) => log.warn(&format!("Error checking log files: {}", e)),
+                        }
+                        
+                        // Run the monitor command of user-defined service types
+                        if global.custom_handler(&service.service_type).is_some() {
+                            match create_service_handler(&service, &global) {
+                                Ok(handler) => match handler.monitor().await {
+                                    Ok(problems) => {
+                                        for line in problems {
+                                            log.warn(&line);
+                                        }
+                                    },
+                                    Err(e) => log.warn(&format!("Error running monitor command: {}", e)),
+                                },
+                                Err(e) => log.warn(&format!("Error creating service handler: {}", e)),
+                            }
+                        }
+                    }
+                    
+                    record_check(&service_name);
+                    if let Some((failures, duration)) = streak.record_success() {
+                        report_recovery(&service, &global, failures, duration).await;
                     }
+                    
+                    if updated { CycleOutcome::Updated } else { CycleOutcome::NoChanges }
+                },
+                Err(e) if git::is_auth_error(&e) => {
+                    streak.record_failure();
+                    counters.record_failure();
+                    log.error(&format!("{}", e));
+                    log.error("Git credentials were rejected; update the deploy key or token for this repository");
+                    CycleOutcome::Failed
+                }
+                Err(e) if git::is_timeout_error(&e) => {
+                    streak.record_failure();
+                    counters.record_failure();
+                    log.error(&format!("{:#}", e));
+                    log.error("The git remote may be unreachable; the check will be retried next interval");
+                    CycleOutcome::Failed
+                }
+                Err(e) => {
+                    streak.record_failure();
+                    counters.record_failure();
+                    log.error(&format!("Error checking for updates: {}", e));
+                    CycleOutcome::Failed
+                }
+            };
+            
+            Ok::<CycleOutcome, anyhow::Error>(outcome)
+        }).await;
+        
+        let outcome = match cycle_result {
+            // With the circuit breaker enabled, bad content pauses updates instead of stopping the task
+            Ok(Err(e)) if is_validation_error(&e) && global.circuit_breaker_threshold > 0 => {
+                streak.record_failure();
+                counters.record_failure();
+                let cooldown = global.circuit_breaker_cooldown_duration();
+                if breaker.record_failure(global.circuit_breaker_threshold, cooldown) {
+                    report_circuit_open(&service, &global, breaker.failures, cooldown).await;
+                    CycleOutcome::CircuitOpen
+                } else {
+                    CycleOutcome::Failed
                 }
-            },
-            Err(e) => {
-                error!("[{}] Error checking for updates: {}", service_name, e);
             }
+            Ok(result) => result?,
+            Err(_) => {
+                streak.record_failure();
+                counters.record_failure();
+                log.error(&format!("Cycle did not finish within {} seconds, aborting it", cycle_timeout.as_secs()));
+                CycleOutcome::TimedOut
+            }
+        };
+        
+        drop(cycle);
+        counters.record_check();
+        
+        if let Some(path) = &global.status_file {
+            write_status(&service, &global, outcome, streak.failures, path).await;
+        }
+        
+        // Give up so the orchestrator can recreate the container or alert
+        let max_failures = global.max_failures_before_exit;
+        if max_failures > 0 && streak.failures >= max_failures {
+            log.error(&format!("Giving up after {} consecutive failed checks", streak.failures));
+            return Err(anyhow!("Service '{}' failed {} consecutive checks", service_name, streak.failures));
         }
         
-        // Wait for next check interval
-        debug!("[{}] Sleeping for {} seconds", service_name, watch_interval.as_secs());
-        sleep(watch_interval).await;
+        // Wait for next check interval, or until a webhook or dependent service requests an immediate check
+        let delay = streak.backoff(watch_interval, global.max_backoff_duration());
+        if delay > watch_interval {
+            log.warn(&format!("{} consecutive failed checks, backing off for {} seconds", streak.failures, delay.as_secs()));
+        } else {
+            log.debug(&format!("Sleeping for {} seconds", delay.as_secs()));
+        }
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = trigger.notified() => log.info("Immediate check requested, checking now"),
+            _ = shutdown.cancelled() => {}
+        }
+    }
+}
+
+/// Record the state of a service after a cycle in the status file
+async fn write_status(service: &ServiceConfig, global: &GlobalSettings, outcome: CycleOutcome, failure_streak: u32, path: &Path) {
+    // Skip collecting the state when it cannot be written anyway
+    if !status_file::writable(path) {
+        return;
+    }
+    
+    let commit = git_service::current_commit(service, global).await.ok().flatten();
+    let container_status = check_service_status(service).await.ok().map(|status| format!("{:?}", status));
+    let healthy = match create_service_handler(service, global) {
+        Ok(handler) => handler.health().await.ok(),
+        Err(_) => None,
+    };
+    
+    let state = ServiceState {
+        last_check: Utc::now().to_rfc3339(),
+        commit,
+        container_status,
+        healthy,
+        last_outcome: outcome,
+        failure_streak,
+    };
+    
+    if let Err(e) = status_file::record(&service.name, state, path) {
+        if !status_file::record_failure(path, &e) {
+            debug!(service = service.name; "Failed to write status file: {:#}", e);
+        } else if status_file::writable(path) {
+            warn!(service = service.name; "Failed to write status file: {:#}", e);
+        } else {
+            warn!("Cannot write status file {}, no longer writing it (set status_file to a writable path or null): {:#}", 
+                  path.display(), e);
+        }
+    }
+}
+
+/// Validate the remote tip of a service's branch in a staged checkout before it is pulled into the live tree
+async fn validate_staged_update(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    let Some(cmd) = service.effective_validation_command(global) else {
+        return Ok(());
+    };
+    
+    let Some(staged) = git_service::stage_update(service, global).await? else {
+        return Ok(());
+    };
+    
+    info!(service = service.name; "Validating commit {} in staged checkout {}", staged.commit, staged.path().display());
+    let result = run_staged_validation(service, &cmd, staged.path()).await;
+    let commit = staged.commit.clone();
+    staged.discard().await;
+    
+    if let Err(e) = result {
+        error!(service = service.name; "Commit {} failed validation, leaving the live tree untouched: {}", commit, e);
+        return Err(WatcherError::ValidationFailed { service: service.name.clone() }.into());
+    }
+    
+    Ok(())
+}
+
+/// Revert the checkout after a failed validation, unless the service keeps broken updates for inspection
+async fn revert_failed_update(service: &ServiceConfig, global: &GlobalSettings) -> bool {
+    if !service.effective_revert_on_failure(global) {
+        warn!(service = service.name; "Revert on failure disabled, leaving the failed update checked out");
+        return false;
+    }
+    
+    info!(service = service.name; "Reverting changes after failed validation");
+    if let Err(e) = git_service::revert_changes(service, global).await {
+        error!(service = service.name; "Failed to revert changes: {}", e);
+        return false;
     }
+    
+    true
+}
+
+/// Wait for the service to be running, its readiness command to pass and its health URL to
+/// return a success status, then check the container's networks after a restart, reverting
+/// and restarting again if any of it fails
+async fn ensure_ready_after_restart(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    let checks = async {
+        ensure_service_ready(service).await?;
+        wait_for_readiness_command(service, global).await?;
+        wait_for_health_url(service).await?;
+        check_expected_networks(service).await
+    };
+    
+    let Err(e) = checks.await else {
+        return Ok(());
+    };
+    
+    error!(service = service.name; "{}", e);
+    if revert_failed_update(service, global).await {
+        info!(service = service.name; "Restarting service on the reverted configuration");
+        match restart_service(service, global).await {
+            Ok(()) => stats::counters(&service.name).record_restart(),
+            Err(e) => error!(service = service.name; "Failed to restart service after revert: {}", e),
+        }
+    }
+    
+    Err(anyhow!("Post-restart checks failed for service {}", service.name))
 }
 
 /// Handle Nginx-specific service updates
@@ -211,156 +1088,118 @@ async fn handle_nginx_update(service: &ServiceConfig, global: &GlobalSettings, i
         .context(format!("Failed to create Nginx config for service {}", service_name))?;
     
     // Run validation command if specified
-    if let Some(cmd) = &service.validation_command {
-        info!("[{}] Running validation command", service_name);
-        if let Err(e) = run_validation(service, cmd).await {
-            error!("[{}] Validation failed: {}", service_name, e);
+    if let Some(cmd) = service.effective_validation_command(global) {
+        info!(service = service_name; "Running validation command");
+        if let Err(e) = run_validation(service, &cmd).await {
+            error!(service = service_name; "Validation failed: {}", e);
             
-            // If auto-fix is enabled, attempt to fix by reverting changes
-            if service.effective_auto_fix(global.auto_fix) {
-                info!("[{}] Auto-fix enabled, attempting to revert changes", service_name);
-                if let Err(e) = git_service::revert_changes(service, global).await {
-                    error!("[{}] Failed to revert changes: {}", service_name, e);
-                }
-            }
+            record_validation_failure(service, global).await;
+            revert_failed_update(service, global).await;
             
-            return Err(anyhow!("Validation failed for service {}", service_name));
+            return Err(WatcherError::ValidationFailed { service: service_name.to_string() }.into());
         }
     }
+    clear_validation_failure(service);
+    
+    // Warn about server blocks shadowing each other, which nginx -t does not flag
+    if let Err(e) = check_server_conflicts(service, global) {
+        warn!(service = service_name; "Error checking for conflicting server blocks: {}", e);
+    }
     
     // Apply permission fixes if configured
     if service.effective_fix_permissions(global.fix_permissions) {
         if let Some(perms) = &service.permissions {
-            info!("[{}] Fixing permissions to {}:{}", service_name, perms.user, perms.group);
-            if let Err(e) = fix_permissions(service, perms).await {
-                warn!("[{}] Failed to fix permissions: {}", service_name, e);
+            info!(service = service_name; "Fixing permissions to {}:{}", perms.user, perms.group);
+            if let Err(e) = fix_permissions(service, perms, global).await {
+                warn!(service = service_name; "Failed to fix permissions: {}", e);
             }
         }
     }
     
+    // Run the pre-apply hook, aborting the update if it fails
+    if let Err(e) = run_pre_apply_hook(service).await {
+        error!(service = service_name; "{}", e);
+        return Err(e);
+    }
+    
     // Restart service if not disabled
     if !service.disable_restart && !global.disable_restart {
-        info!("[{}] Restarting Nginx service", service_name);
+        ensure_startable(service, global).await?;
+        info!(service = service_name; "Restarting Nginx service");
         if let Err(e) = restart_nginx(&nginx_config).await {
-            error!("[{}] Failed to restart Nginx: {}", service_name, e);
+            error!(service = service_name; "Failed to restart Nginx: {}", e);
             return Err(e.into());
         }
+        stats::counters(service_name).record_restart();
+        
+        ensure_ready_after_restart(service, global).await?;
+    }
+    
+    // Run the post-apply hook; a failure here does not revert the update
+    if let Err(e) = run_post_apply_hook(service).await {
+        warn!(service = service_name; "{}", e);
     }
     
     // Check logs if monitoring is enabled
     if service.effective_monitor_logs(global.monitor_logs) {
         if let Err(e) = check_nginx_logs(&nginx_config).await {
-            warn!("[{}] Error checking Nginx logs: {}", service_name, e);
+            warn!(service = service_name; "Error checking Nginx logs: {}", e);
         }
     }
     
     Ok(())
 }
 
-/// Handle Apache-specific service updates
-async fn handle_apache_update(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
-    let service_name = &service.name;
+/// Handle generic service updates
+async fn handle_generic_update(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    let handler = create_service_handler(service, global)?;
+    let service_name = handler.name();
+    info!(service = service_name; "Applying update for {:?} service", handler.service_type());
     
     // Run validation if specified
-    if let Some(cmd) = &service.validation_command {
-        info!("[{}] Running validation command", service_name);
-        if let Err(e) = run_validation(service, cmd).await {
-            error!("[{}] Validation failed: {}", service_name, e);
-            
-            // If auto-fix is enabled, revert changes
-            if service.effective_auto_fix(global.auto_fix) {
-                info!("[{}] Auto-fix enabled, attempting to revert changes", service_name);
-                if let Err(e) = git_service::revert_changes(service, global).await {
-                    error!("[{}] Failed to revert changes: {}", service_name, e);
-                }
-            }
-            
-            return Err(anyhow!("Validation failed for service {}", service_name));
-        }
+    if !handler.validate().await? {
+        error!(service = service_name; "Validation failed");
+        
+        record_validation_failure(service, global).await;
+        revert_failed_update(service, global).await;
+        
+        return Err(WatcherError::ValidationFailed { service: service_name.to_string() }.into());
     }
+    clear_validation_failure(service);
     
-    // Apply permission fixes
-    if service.effective_fix_permissions(global.fix_permissions) {
-        if let Some(perms) = &service.permissions {
-            info!("[{}] Fixing permissions to {}:{}", service_name, perms.user, perms.group);
-            if let Err(e) = fix_permissions(service, perms).await {
-                warn!("[{}] Failed to fix permissions: {}", service_name, e);
-            }
+    // Apply service-specific fixes if configured
+    if service.effective_auto_fix(global.auto_fix) {
+        if let Err(e) = handler.fix_issues().await {
+            warn!(service = service_name; "Failed to fix issues: {}", e);
         }
     }
     
-    // Restart service
-    if !service.disable_restart && !global.disable_restart {
-        info!("[{}] Restarting Apache service", service_name);
-        if let Err(e) = restart_service(service, global).await {
-            error!("[{}] Failed to restart Apache: {}", service_name, e);
-            return Err(e.into());
-        }
-    }
-    
-    Ok(())
-}
-
-/// Handle generic service updates
-async fn handle_generic_update(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
-    let service_name = &service.name;
-    
-    // Run validation if specified
-    if let Some(cmd) = &service.validation_command {
-        info!("[{}] Running validation command", service_name);
-        if let Err(e) = run_validation(service, cmd).await {
-            error!("[{}] Validation failed: {}", service_name, e);
-            
-            // If auto-fix is enabled, revert changes
-            if service.effective_auto_fix(global.auto_fix) {
-                info!("[{}] Auto-fix enabled, attempting to revert changes", service_name);
-                if let Err(e) = git_service::revert_changes(service, global).await {
-                    error!("[{}] Failed to revert changes: {}", service_name, e);
-                }
-            }
-            
-            return Err(anyhow!("Validation failed for service {}", service_name));
-        }
+    // Apply permission fixes
+    if let Err(e) = handler.fix_permissions().await {
+        warn!(service = service_name; "Failed to fix permissions: {}", e);
     }
     
-    // Apply permission fixes
-    if service.effective_fix_permissions(global.fix_permissions) {
-        if let Some(perms) = &service.permissions {
-            info!("[{}] Fixing permissions to {}:{}", service_name, perms.user, perms.group);
-            if let Err(e) = fix_permissions(service, perms).await {
-                warn!("[{}] Failed to fix permissions: {}", service_name, e);
-            }
-        }
+    // Run the pre-apply hook, aborting the update if it fails
+    if let Err(e) = run_pre_apply_hook(service).await {
+        error!(service = service_name; "{}", e);
+        return Err(e);
     }
     
     // Restart service
+    if let Err(e) = handler.restart().await {
+        error!(service = service_name; "Failed to restart service: {}", e);
+        return Err(e);
+    }
     if !service.disable_restart && !global.disable_restart {
-        info!("[{}] Restarting service", service_name);
-        if let Err(e) = restart_service(service, global).await {
-            error!("[{}] Failed to restart service: {}", service_name, e);
-            return Err(e.into());
-        }
+        stats::counters(service_name).record_restart();
+        
+        ensure_ready_after_restart(service, global).await?;
     }
     
-    Ok(())
-}
-
-/// Parse a duration string (e.g., "30s", "5m") into a Duration
-fn parse_duration(duration_str: &str) -> Result<Duration> {
-    let len = duration_str.len();
-    if len < 2 {
-        return Err(anyhow!("Invalid duration format: {}", duration_str));
+    // Run the post-apply hook; a failure here does not revert the update
+    if let Err(e) = run_post_apply_hook(service).await {
+        warn!(service = service_name; "{}", e);
     }
     
-    let (value_str, unit) = duration_str.split_at(len - 1);
-    let value = value_str.parse::<u64>()
-        .context(format!("Failed to parse duration value: {}", value_str))?;
-    
-    match unit {
-        "s" => Ok(Duration::from_secs(value)),
-        "m" => Ok(Duration::from_secs(value * 60)),
-        "h" => Ok(Duration::from_secs(value * 3600)),
-        "d" => Ok(Duration::from_secs(value * 86400)),
-        _ => Err(anyhow!("Invalid duration unit: {}", unit)),
-    }
+    Ok(())
 }
\ No newline at end of file
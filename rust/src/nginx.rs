@@ -1,20 +1,30 @@
 use anyhow::{anyhow, Context, Result};
-use log::{info, warn};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{debug, info, warn};
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::{PathBuf};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{LazyLock, Mutex};
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use walkdir::WalkDir;
 use async_trait::async_trait;
 
 use crate::config::{GlobalSettings, Permissions, ServiceConfig, ServiceType, nginx::Config as NginxConfig};
 use crate::docker_utils::{
-    ContainerStatus, DockerComposeConfig, check_container_status, 
-    get_container_logs, recreate_with_docker_compose, restart_container, 
-    restart_with_docker_compose
+    ContainerStatus, DockerComposeConfig, check_container_status, container_command,
+    get_container_logs, recreate_with_docker_compose, resolve_container_name, 
+    restart_container, restart_with_docker_compose
 };
+use crate::service::{check_health, restart_service, shell_command, ServiceHandler};
+use crate::utils::ensure_safe_permission_path;
+
+/// Time of the last successful log check per container, so each check only scans new entries
+static LAST_LOG_CHECK: LazyLock<Mutex<HashMap<String, DateTime<Utc>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// Check the status of the Nginx container
 pub async fn check_nginx_status(config: &NginxConfig) -> Result<ContainerStatus> {
@@ -33,7 +43,7 @@ pub async fn restart_nginx(config: &NginxConfig) -> Result<()> {
     if config.use_docker_compose {
         restart_nginx_with_compose(config).await
     } else {
-        restart_container(&config.nginx_container_name).await
+        restart_container(&config.nginx_container_name, config.retry_attempts, config.retry_delay, config.restarting_grace).await
     }
 }
 
@@ -42,7 +52,10 @@ async fn restart_nginx_with_compose(config: &NginxConfig) -> Result<()> {
     // Determine if we need a full recreate (down, build, up) or just a restart
     let compose_config = DockerComposeConfig {
         compose_dir: config.compose_dir.clone(),
-        compose_file: Some(config.compose_file.clone()),
+        compose_files: config.compose_files.clone(),
+        compose_profiles: config.compose_profiles.clone(),
+        env_file: config.env_file.clone(),
+        ready_timeout: config.ready_timeout,
         service_name: config.nginx_container_name.clone(),
     };
     
@@ -54,6 +67,14 @@ async fn restart_nginx_with_compose(config: &NginxConfig) -> Result<()> {
     }
 }
 
+/// Time the output of a container was last checked for errors, None before the first check
+fn last_log_check(container_name: &str) -> Result<Option<DateTime<Utc>>> {
+    Ok(LAST_LOG_CHECK.lock()
+        .map_err(|_| anyhow!("Log check state lock poisoned"))?
+        .get(container_name)
+        .copied())
+}
+
 /// Check Nginx logs for errors
 pub async fn check_nginx_logs(config: &NginxConfig) -> Result<()> {
     if !config.monitor_logs {
@@ -69,25 +90,46 @@ pub async fn check_nginx_logs(config: &NginxConfig) -> Result<()> {
         return Ok(());
     }
     
-    // Get logs from the container
-    let logs = get_container_logs(&config.nginx_container_name, config.log_tail_lines).await?;
+    // Get logs written since the previous check
+    let since = last_log_check(&config.nginx_container_name)?;
+    let checked_at = Utc::now();
+    
+    let logs = get_container_logs(&config.nginx_container_name, config.log_tail_lines, since, config.log_streams).await?;
+    
+    LAST_LOG_CHECK.lock()
+        .map_err(|_| anyhow!("Log check state lock poisoned"))?
+        .insert(config.nginx_container_name.clone(), checked_at);
     
     // Check for errors
-    let errors: Vec<&str> = logs.lines()
-        .filter(|line| line.to_lowercase().contains("error"))
+    let errors: Vec<(&str, &str)> = logs.lines()
+        .filter(|(_, line)| line.to_lowercase().contains("error"))
         .collect();
     
     if !errors.is_empty() {
-        warn!("Found {} errors in Nginx logs:", errors.len());
+        // Group each stream on its own, so every example says where it was written
+        let mut grouped: Vec<(&str, String, usize)> = Vec::new();
+        for stream in ["stdout", "stderr"] {
+            let lines: Vec<&str> = errors.iter()
+                .filter(|(s, _)| *s == stream)
+                .map(|(_, line)| *line)
+                .collect();
+            grouped.extend(group_log_errors(&lines).into_iter().map(|(error, count)| (stream, error, count)));
+        }
+        grouped.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+        warn!("Found {} errors ({} unique) in Nginx logs", errors.len(), grouped.len());
         
-        // Show the first few errors
-        for (i, error) in errors.iter().take(5).enumerate() {
-            warn!("[{}] NGINX: {}", i + 1, error);
+        // Show the most frequent errors
+        for (i, (stream, error, count)) in grouped.iter().take(config.log_error_examples).enumerate() {
+            if *count > 1 {
+                warn!(service = config.nginx_container_name; "#{} NGINX {} ({} occurrences): {}", i + 1, stream, count, error);
+            } else {
+                warn!(service = config.nginx_container_name; "#{} NGINX {}: {}", i + 1, stream, error);
+            }
         }
         
         // Count 403 errors
         let count_403 = errors.iter()
-            .filter(|line| line.contains("403"))
+            .filter(|(_, line)| line.contains("403"))
             .count();
         
         if count_403 > 0 {
@@ -98,15 +140,88 @@ pub async fn check_nginx_logs(config: &NginxConfig) -> Result<()> {
     Ok(())
 }
 
+/// Patterns for the variable parts of log lines (timestamps, process and connection ids, addresses)
+static LOG_NORMALIZE_PATTERNS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    vec![
+        (Regex::new(r"^\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2} ").unwrap(), ""),
+        (Regex::new(r"\[\d{2}/\w{3}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4}\]").unwrap(), "[<time>]"),
+        (Regex::new(r"\b\d+#\d+: ").unwrap(), ""),
+        (Regex::new(r"\*\d+ ").unwrap(), ""),
+        (Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b").unwrap(), "<ip>"),
+    ]
+});
+
+/// Strip the parts of a log line that differ between occurrences of the same error
+fn normalize_log_line(line: &str) -> String {
+    let mut normalized = line.trim().to_string();
+    
+    for (pattern, replacement) in LOG_NORMALIZE_PATTERNS.iter() {
+        normalized = pattern.replace_all(&normalized, *replacement).into_owned();
+    }
+    
+    normalized
+}
+
+/// Group log lines by normalized message, sorted by occurrence count (most frequent first)
+fn group_log_errors(errors: &[&str]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    
+    for error in errors {
+        let normalized = normalize_log_line(error);
+        let count = counts.entry(normalized.clone()).or_insert(0);
+        if *count == 0 {
+            order.push(normalized);
+        }
+        *count += 1;
+    }
+    
+    // Stable sort keeps first-seen order among equally frequent errors
+    let mut grouped: Vec<(String, usize)> = order.into_iter()
+        .map(|error| {
+            let count = counts[&error];
+            (error, count)
+        })
+        .collect();
+    grouped.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    
+    grouped
+}
+
 //----------------------------------------
 // Extended Nginx Service Implementation
 //----------------------------------------
 
+/// Recursively change ownership of a tree; -h changes symlinks themselves and -P never follows them,
+/// so nothing outside the tree is touched
+async fn chown_no_follow(owner: &str, path: &Path) -> std::io::Result<ExitStatus> {
+    Command::new("chown")
+        .args(["-R", "-h", "-P", owner])
+        .arg(path)
+        .status()
+        .await
+}
+
+/// Default number of days before expiry at which certificates are reported
+const DEFAULT_CERT_EXPIRY_THRESHOLD_DAYS: u64 = 14;
+
+/// Error log read inside the container when `error_log_path` is not configured
+const DEFAULT_ERROR_LOG_PATH: &str = "/var/log/nginx/error.log";
+
+/// Headers added by `enhance_security` when `security_headers` is not configured
+const DEFAULT_SECURITY_HEADERS: &[&str] = &[
+    "X-Content-Type-Options: nosniff",
+    "X-Frame-Options: SAMEORIGIN",
+    "X-XSS-Protection: 1; mode=block",
+];
+
 /// Nginx service handler - implements full management capabilities
 pub struct NginxService<'a> {
     service: &'a ServiceConfig,
     global: &'a GlobalSettings,
     custom_settings: HashMap<String, String>,
+    /// Headers `enhance_security` adds, as `(name, value)`
+    security_headers: Vec<(String, String)>,
 }
 
 impl<'a> NginxService<'a> {
@@ -138,63 +253,121 @@ impl<'a> NginxService<'a> {
             custom_settings.insert("enable_dir_listing".to_string(), "false".to_string());
         }
         
+        // Extract index file creation settings (opt-in, since it hides autoindex listings)
+        let create_missing_index = service.custom_settings.get("create_missing_index")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        custom_settings.insert("create_missing_index".to_string(), create_missing_index.to_string());
+        
+        if let Some(template) = service.custom_settings.get("index_template").and_then(|value| value.as_str()) {
+            custom_settings.insert("index_template".to_string(), template.to_string());
+        }
+        
+        // Extract the error log path inside the container
+        let error_log_path = service.custom_settings.get("error_log_path")
+            .and_then(|value| value.as_str())
+            .unwrap_or(DEFAULT_ERROR_LOG_PATH);
+        custom_settings.insert("error_log_path".to_string(), error_log_path.to_string());
+        
+        // Extract certificate expiry warning threshold
+        let threshold_days = service.custom_settings.get("cert_expiry_threshold_days")
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_CERT_EXPIRY_THRESHOLD_DAYS);
+        custom_settings.insert("cert_expiry_threshold_days".to_string(), threshold_days.to_string());
+        
+        // Extract the security headers to inject, written as "Name: value"
+        let security_headers = match service.custom_settings.get("security_headers") {
+            Some(value) => value.as_array()
+                .and_then(|headers| headers.iter().map(|header| header.as_str()).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| anyhow!("custom_settings.security_headers must be a list of \"Name: value\" strings"))?,
+            None => DEFAULT_SECURITY_HEADERS.to_vec(),
+        };
+        let security_headers = security_headers.into_iter()
+            .map(|header| header.split_once(':')
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .ok_or_else(|| anyhow!("Invalid security header '{}', expected \"Name: value\"", header)))
+            .collect::<Result<Vec<_>>>()?;
+        
         Ok(Self {
             service,
             global,
             custom_settings,
+            security_headers,
         })
     }
 
+    /// Whether missing index files should be created
+    ///
+    /// Never true when directory listing is enabled, since an index would hide the listing.
+    fn should_create_index(&self) -> bool {
+        let enabled = |key: &str| self.custom_settings.get(key).map(|v| v == "true").unwrap_or(false);
+        enabled("create_missing_index") && !enabled("enable_dir_listing")
+    }
+    
+    /// Get the HTML for created index files, from the configured template if any
+    fn index_html(&self) -> Result<String> {
+        /// Body of created index files when no template is configured
+        const DEFAULT_INDEX_HTML: &str = "<!DOCTYPE html>\n<html>\n<head>\n  <title>Welcome</title>\n</head>\n<body>\n  <h1>Welcome</h1>\n  <p>Site under construction</p>\n</body>\n</html>\n";
+        
+        match self.custom_settings.get("index_template") {
+            Some(template) => fs::read_to_string(template)
+                .context(format!("Failed to read index template: {}", template)),
+            None => Ok(DEFAULT_INDEX_HTML.to_string()),
+        }
+    }
+
     /// Get the path to the Nginx configuration directory
     pub fn get_config_path(&self) -> PathBuf {
-        self.service.local_path.clone()
+        self.service.config_dir()
     }
 
     /// Validate Nginx configuration
     pub async fn validate_config(&self) -> Result<bool> {
-        info!("[{}] Validating Nginx configuration", self.service.name);
+        info!(service = self.service.name; "Validating Nginx configuration");
+        
+        // Duplicate virtual hosts pass nginx -t, so report them separately
+        self.check_server_conflicts()?;
         
         // Try to use validation command if available
         if let Some(cmd) = &self.service.validation_command {
-            info!("[{}] Running validation command: {}", self.service.name, cmd);
+            info!(service = self.service.name; "Running validation command: {}", cmd);
             
-            let status = Command::new("sh")
-                .arg("-c")
-                .arg(cmd)
+            let status = shell_command(self.service, cmd)
                 .status()
                 .await
                 .context("Failed to execute validation command")?;
                 
             if !status.success() {
-                warn!("[{}] Validation command failed", self.service.name);
+                warn!(service = self.service.name; "Validation command failed");
                 return Ok(false);
             }
             
-            info!("[{}] Validation command succeeded", self.service.name);
+            info!(service = self.service.name; "Validation command succeeded");
             return Ok(true);
         }
         
         // Fall back to standard nginx -t validation
-        info!("[{}] No validation command specified, using standard nginx -t", self.service.name);
+        info!(service = self.service.name; "No validation command specified, using standard nginx -t");
         
-        let status = Command::new("docker")
-            .args(&["exec", &self.service.container_name, "nginx", "-t"])
+        let container_name = resolve_container_name(&self.service.container_name).await?;
+        let status = container_command()
+            .args(&["exec", &container_name, "nginx", "-t"])
             .status()
             .await
             .context("Failed to execute nginx -t")?;
             
         if !status.success() {
-            warn!("[{}] Nginx configuration test failed", self.service.name);
+            warn!(service = self.service.name; "Nginx configuration test failed");
             return Ok(false);
         }
         
-        info!("[{}] Nginx configuration test passed", self.service.name);
+        info!(service = self.service.name; "Nginx configuration test passed");
         return Ok(true);
     }
     
     /// Find all Nginx configuration files
     pub fn find_config_files(&self) -> Result<Vec<PathBuf>> {
-        let dir = &self.service.local_path;
+        let dir = self.service.config_dir();
         let mut config_files = Vec::new();
         
         if !dir.exists() || !dir.is_dir() {
@@ -223,17 +396,17 @@ impl<'a> NginxService<'a> {
             return Ok(());
         }
         
-        info!("[{}] Analyzing and fixing common Nginx configuration issues", self.service.name);
+        info!(service = self.service.name; "Analyzing and fixing common Nginx configuration issues");
         
         let config_files = self.find_config_files()?;
         
         if config_files.is_empty() {
-            warn!("[{}] No Nginx configuration files found in {}", 
-                  self.service.name, self.service.local_path.display());
+            warn!(service = self.service.name; "No Nginx configuration files found in {}", 
+                                               self.service.local_path.display());
             return Ok(());
         }
         
-        info!("[{}] Found {} Nginx configuration files", self.service.name, config_files.len());
+        info!(service = self.service.name; "Found {} Nginx configuration files", config_files.len());
         
         // Get directory listing setting
         let enable_dir_listing = self.custom_settings.get("enable_dir_listing")
@@ -248,7 +421,7 @@ impl<'a> NginxService<'a> {
             
             // Fix directory listing if requested
             if enable_dir_listing && content.contains("autoindex off;") {
-                info!("[{}] Enabling directory listing in {}", self.service.name, config_file.display());
+                info!(service = self.service.name; "Enabling directory listing in {}", config_file.display());
                 
                 let new_content = content.replace("autoindex off;", "autoindex on;");
                 
@@ -258,7 +431,12 @@ impl<'a> NginxService<'a> {
                 }
             }
             
-            // Check for root directories and ensure they have index files
+            // Check for root directories and ensure they have index files, unless this
+            // file serves directory listings
+            if !self.should_create_index() || content.contains("autoindex on;") {
+                continue;
+            }
+            
             let root_pattern = Regex::new(r"root\s+([^;]+)")?;
             
             for cap in root_pattern.captures_iter(&content) {
@@ -274,9 +452,10 @@ impl<'a> NginxService<'a> {
                     
                     // Create directory if it doesn't exist
                     if !path.exists() {
-                        info!("[{}] Creating directory: {}", self.service.name, path.display());
+                        info!(service = self.service.name; "Creating directory: {}", path.display());
                         fs::create_dir_all(&path)
                             .context(format!("Failed to create directory: {}", path.display()))?;
+                        self.apply_created_ownership(&path, 0o755).await;
                     }
                     
                     if path.is_dir() {
@@ -291,24 +470,56 @@ impl<'a> NginxService<'a> {
                             });
                         
                         if !has_index {
-                            info!("[{}] Creating default index.html in {}", self.service.name, path.display());
+                            info!(service = self.service.name; "Creating default index.html in {}", path.display());
                             
                             let index_path = path.join("index.html");
                             let mut file = File::create(&index_path)
                                 .context(format!("Failed to create index file: {}", index_path.display()))?;
                             
-                            file.write_all(b"<!DOCTYPE html>\n<html>\n<head>\n  <title>Welcome</title>\n</head>\n<body>\n  <h1>Welcome</h1>\n  <p>Site under construction</p>\n</body>\n</html>\n")
+                            file.write_all(self.index_html()?.as_bytes())
                                 .context("Failed to write default content to index.html")?;
+                            self.apply_created_ownership(&index_path, 0o644).await;
                         }
                     }
                 }
             }
         }
         
-        info!("[{}] Completed checking and fixing common issues", self.service.name);
+        info!(service = self.service.name; "Completed checking and fixing common issues");
         Ok(())
     }
     
+    /// Give a file or directory created by the fixer the configured owner and mode,
+    /// matching what the container-side fixer does, so nginx can read it
+    async fn apply_created_ownership(&self, path: &Path, mode: u32) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+                warn!(service = self.service.name; "Failed to set mode {:o} on {}: {}", mode, path.display(), e);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        
+        let permissions = match &self.service.permissions {
+            Some(permissions) => permissions,
+            None => return,
+        };
+        
+        let owner = format!("{}:{}", permissions.user, permissions.group);
+        let status = Command::new("chown")
+            .args([owner.as_str(), &path.to_string_lossy()])
+            .status()
+            .await;
+        
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(_) => warn!(service = self.service.name; "Failed to set owner {} on {}", owner, path.display()),
+            Err(e) => warn!(service = self.service.name; "Failed to set owner {} on {}: {}", owner, path.display(), e),
+        }
+    }
+    
     /// Fix permissions for Nginx files
     pub async fn fix_permissions(&self) -> Result<()> {
         if !self.service.effective_fix_permissions(self.global.fix_permissions) {
@@ -316,11 +527,11 @@ impl<'a> NginxService<'a> {
         }
         
         if let Some(permissions) = &self.service.permissions {
-            info!("[{}] Fixing permissions for Nginx files", self.service.name);
+            info!(service = self.service.name; "Fixing permissions for Nginx files");
             self.fix_local_permissions(permissions).await?;
             self.fix_container_permissions(permissions).await?;
         } else {
-            info!("[{}] No permission settings found, using defaults", self.service.name);
+            info!(service = self.service.name; "No permission settings found, using defaults");
             // Use default permissions
             let default_permissions = Permissions {
                 fix: true,
@@ -342,64 +553,45 @@ impl<'a> NginxService<'a> {
             return Ok(());
         }
         
-        info!("[{}] Setting permissions for local repo at {}", 
-              self.service.name, repo_path.display());
+        ensure_safe_permission_path(repo_path, self.global)?;
+        
+        info!(service = self.service.name; "Setting permissions for local repo at {}", 
+                                           repo_path.display());
         
         let owner = format!("{}:{}", permissions.user, permissions.group);
+        let path = repo_path.to_string_lossy();
         
         // Try to fix with user/group names
-        let status = Command::new("chown")
-            .args(&["-R", &owner, &repo_path.to_string_lossy()])
-            .status()
-            .await;
-            
-        if let Err(e) = status {
-            warn!("[{}] Failed to set permissions with named users: {}", self.service.name, e);
+        let status = chown_no_follow(&owner, repo_path).await;
+        
+        if !matches!(status, Ok(status) if status.success()) {
+            warn!(service = self.service.name; "Failed to set ownership with named users: {:?}", status);
             
             // Try with numeric IDs if available
             if let (Ok(uid), Ok(gid)) = (std::env::var("USER_ID"), std::env::var("GROUP_ID")) {
-                info!("[{}] Trying with numeric IDs: {}:{}", self.service.name, uid, gid);
+                info!(service = self.service.name; "Trying with numeric IDs: {}:{}", uid, gid);
                 
-                let numeric_owner = format!("{}:{}", uid, gid);
-                let status = Command::new("chown")
-                    .args(&["-R", &numeric_owner, &repo_path.to_string_lossy()])
-                    .status()
-                    .await;
-                    
-                if let Err(e) = status {
-                    warn!("[{}] Failed to set permissions with numeric IDs: {}", self.service.name, e);
+                let status = chown_no_follow(&format!("{}:{}", uid, gid), repo_path).await;
+                if !matches!(status, Ok(status) if status.success()) {
+                    warn!(service = self.service.name; "Failed to set ownership with numeric IDs: {:?}", status);
                 }
             }
         }
         
-        // Set directory permissions
-        let dir_chmod_status = Command::new("find")
+        // Set directory and file modes in one pass; -type does not match symlinks under -P
+        let chmod_status = Command::new("find")
             .args([
-                &repo_path.to_string_lossy(),
-                "-type", "d",
-                "-exec", "chmod", "750", "{}", ";"
+                "-P", &path,
+                "(", "-type", "d", "-exec", "chmod", "750", "{}", "+", ")",
+                "-o",
+                "(", "-type", "f", "-exec", "chmod", "640", "{}", "+", ")",
             ])
             .status()
             .await
-            .context("Failed to set directory permissions")?;
+            .context("Failed to set file and directory permissions")?;
         
-        if !dir_chmod_status.success() {
-            warn!("[{}] Failed to set directory permissions", self.service.name);
-        }
-        
-        // Set file permissions
-        let file_chmod_status = Command::new("find")
-            .args([
-                &repo_path.to_string_lossy(),
-                "-type", "f",
-                "-exec", "chmod", "640", "{}", ";"
-            ])
-            .status()
-            .await
-            .context("Failed to set file permissions")?;
-        
-        if !file_chmod_status.success() {
-            warn!("[{}] Failed to set file permissions", self.service.name);
+        if !chmod_status.success() {
+            warn!(service = self.service.name; "Failed to set file and directory permissions");
         }
         
         Ok(())
@@ -410,17 +602,19 @@ impl<'a> NginxService<'a> {
         // Check if container exists and is running
         let status = check_container_status(&self.service.container_name).await?;
         if status != ContainerStatus::Running {
-            warn!("[{}] Container is not running, skipping container permission fixes", self.service.name);
+            warn!(service = self.service.name; "Container is not running, skipping container permission fixes");
             return Ok(());
         }
         
+        let container_name = resolve_container_name(&self.service.container_name).await?;
+        
         // Get web root
         let web_root = self.custom_settings.get("web_root")
             .unwrap_or(&"/var/www/html".to_string())
             .clone();
             
         // Fix web root permissions inside container
-        info!("[{}] Setting permissions for web root at {}", self.service.name, web_root);
+        info!(service = self.service.name; "Setting permissions for web root at {}", web_root);
         
         let cmd = format!(
             "mkdir -p {} && \
@@ -432,29 +626,54 @@ impl<'a> NginxService<'a> {
             web_root, web_root, web_root, web_root
         );
         
-        let status = Command::new("docker")
-            .args(["exec", "-u", "root", &self.service.container_name, "sh", "-c", &cmd])
+        let status = container_command()
+            .args(["exec", "-u", "root", &container_name, "sh", "-c", &cmd])
             .status()
             .await
             .context("Failed to fix web root permissions")?;
         
         if !status.success() {
-            warn!("[{}] Permission fixing command failed for web root", self.service.name);
+            warn!(service = self.service.name; "Permission fixing command failed for web root");
         }
         
         // Create index files where missing
-        info!("[{}] Creating default index files where missing", self.service.name);
+        if self.should_create_index() {
+            self.create_container_index_files(&container_name, &web_root, permissions).await?;
+        }
+        
+        // Fix Nginx configuration permissions
+        info!(service = self.service.name; "Setting correct permissions for Nginx configuration");
+        
+        let cmd = "chmod -R 644 /etc/nginx/conf.d/*.conf && chmod 644 /etc/nginx/nginx.conf";
+        let status = container_command()
+            .args(["exec", "-u", "root", &container_name, "sh", "-c", &cmd])
+            .status()
+            .await
+            .context("Failed to fix Nginx configuration permissions")?;
+        
+        if !status.success() {
+            warn!(service = self.service.name; "Failed to fix Nginx configuration permissions");
+        }
+        
+        Ok(())
+    }
+    
+    /// Create index files in web root directories inside the container that lack one
+    async fn create_container_index_files(&self, container_name: &str, web_root: &str, permissions: &Permissions) -> Result<()> {
+        info!(service = self.service.name; "Creating default index files where missing");
+        
+        let index_html = self.index_html()?;
+        let owner = format!("{}:{}", permissions.user, permissions.group);
         
         // Get list of all directories in web root
-        let cmd = format!("find {} -type d", web_root);
-        let output = Command::new("docker")
-            .args(["exec", &self.service.container_name, "sh", "-c", &cmd])
+        let output = container_command()
+            .args(["exec", container_name, "find", web_root, "-type", "d"])
             .output()
             .await
             .context("Failed to list directories in web root")?;
         
         if !output.status.success() {
-            warn!("[{}] Failed to list directories in web root", self.service.name);
+            warn!(service = self.service.name; "Failed to list directories in web root");
             return Ok(());
         }
         
@@ -462,68 +681,54 @@ impl<'a> NginxService<'a> {
         
         for dir in dirs.lines() {
             // Check if directory has index files
-            let check_cmd = format!("find {} -maxdepth 1 -name \"index.*\" | grep .", dir);
-            let check_result = Command::new("docker")
-                .args(["exec", &self.service.container_name, "sh", "-c", &check_cmd])
-                .output()
+            let check_result = container_command()
+                .args(["exec", container_name, "sh", "-c", "ls \"$1\"/index.* >/dev/null 2>&1", "sh", dir])
+                .status()
                 .await;
             
-            // If no index files found (grep returns non-zero), create one
-            if check_result.is_err() || !check_result.unwrap().status.success() {
-                info!("[{}] Creating default index.html in {}", self.service.name, dir);
-                
-                let create_cmd = format!(
-                    "echo '<!DOCTYPE html>
-<html>
-<head>
-  <title>Welcome</title>
-</head>
-<body>
-  <h1>Welcome</h1>
-  <p>Site under construction</p>
-</body>
-</html>' > {}/index.html && \
-                    chown {}:{} {}/index.html && \
-                    chmod 644 {}/index.html",
-                    dir, permissions.user, permissions.group, dir, dir
-                );
-                
-                let create_result = Command::new("docker")
-                    .args(["exec", "-u", "root", &self.service.container_name, "sh", "-c", &create_cmd])
-                    .status()
-                    .await;
-                
-                if let Err(e) = create_result {
-                    warn!("[{}] Failed to create index.html in {}: {}", self.service.name, dir, e);
+            if matches!(check_result, Ok(status) if status.success()) {
+                continue;
+            }
+            
+            info!(service = self.service.name; "Creating default index.html in {}", dir);
+            
+            // Pass the HTML on stdin so template content never goes through the shell
+            let create_cmd = "cat > \"$1/index.html\" && chown \"$2\" \"$1/index.html\" && chmod 644 \"$1/index.html\"";
+            let child = container_command()
+                .args(["exec", "-i", "-u", "root", container_name, "sh", "-c", create_cmd, "sh", dir, &owner])
+                .stdin(Stdio::piped())
+                .spawn();
+            
+            let result = match child {
+                Ok(mut child) => {
+                    if let Some(mut stdin) = child.stdin.take() {
+                        stdin.write_all(index_html.as_bytes()).await
+                            .context("Failed to pass index.html content to docker exec")?;
+                    }
+                    child.wait().await
                 }
+                Err(e) => Err(e),
+            };
+            
+            match result {
+                Ok(status) if status.success() => {}
+                Ok(status) => warn!(service = self.service.name; "Failed to create index.html in {}: exit code {:?}", 
+                                                                 dir, status.code()),
+                Err(e) => warn!(service = self.service.name; "Failed to create index.html in {}: {}", dir, e),
             }
         }
         
-        // Fix Nginx configuration permissions
-        info!("[{}] Setting correct permissions for Nginx configuration", self.service.name);
-        
-        let cmd = "chmod -R 644 /etc/nginx/conf.d/*.conf && chmod 644 /etc/nginx/nginx.conf";
-        let status = Command::new("docker")
-            .args(["exec", "-u", "root", &self.service.container_name, "sh", "-c", &cmd])
-            .status()
-            .await
-            .context("Failed to fix Nginx configuration permissions")?;
-        
-        if !status.success() {
-            warn!("[{}] Failed to fix Nginx configuration permissions", self.service.name);
-        }
-        
         Ok(())
     }
     
     /// Enhance Nginx security configuration
     pub async fn enhance_security(&self) -> Result<()> {
-        info!("[{}] Enhancing Nginx security configuration", self.service.name);
+        info!(service = self.service.name; "Enhancing Nginx security configuration");
         
         let config_files = self.find_config_files()?;
         
         if config_files.is_empty() {
-            warn!("[{}] No Nginx configuration files found", self.service.name);
+            warn!(service = self.service.name; "No Nginx configuration files found");
             return Ok(());
         }
         
@@ -531,44 +736,148 @@ impl<'a> NginxService<'a> {
             let content = fs::read_to_string(config_file)
                 .context(format!("Failed to read config file: {}", config_file.display()))?;
             
-            // Check and add security headers if not present
-            if !content.contains("add_header X-Content-Type-Options") {
-                info!("[{}] Adding security headers to {}", self.service.name, config_file.display());
+            // Add the security headers a server block's first location does not set yet
+            let new_content = insert_security_headers(&content, &self.security_headers);
+            if new_content != content {
+                fs::write(config_file, new_content)
+                    .context(format!("Failed to write changes to {}", config_file.display()))?;
                 
-                let mut lines: Vec<String> = content.lines().map(String::from).collect();
-                
-                // Find server blocks
-                for i in 0..lines.len() {
-                    if lines[i].contains("server {") || lines[i].trim() == "server {" {
-                        // Look for a location block to add headers after
-                        for j in i..lines.len() {
-                            if lines[j].contains("location") && lines[j].contains("{") {
-                                // Add security headers inside location block
-                                if j + 1 < lines.len() {
-                                    lines.insert(j + 1, "        # Security headers".to_string());
-                                    lines.insert(j + 2, "        add_header X-Content-Type-Options nosniff;".to_string());
-                                    lines.insert(j + 3, "        add_header X-Frame-Options SAMEORIGIN;".to_string());
-                                    lines.insert(j + 4, "        add_header X-XSS-Protection \"1; mode=block\";".to_string());
-                                    break;
-                                }
-                            }
-                        }
+                info!(service = self.service.name; "Added security headers to {}", config_file.display());
+            }
+        }
+        
+        info!(service = self.service.name; "Security enhancement complete");
+        Ok(())
+    }
+    
+    /// Find server blocks that share a `server_name` and `listen` pair, which silently shadow each other
+    pub fn check_server_conflicts(&self) -> Result<Vec<String>> {
+        // (listen, server_name) -> files declaring a server block for it
+        let mut declared: HashMap<(String, String), Vec<PathBuf>> = HashMap::new();
+        
+        for config_file in self.find_config_files()? {
+            let content = fs::read_to_string(&config_file)
+                .context(format!("Failed to read config file: {}", config_file.display()))?;
+            
+            for block in parse_server_blocks(&content) {
+                for listen in &block.listens {
+                    for name in &block.server_names {
+                        declared.entry((listen.clone(), name.clone()))
+                            .or_default()
+                            .push(config_file.clone());
                     }
                 }
-                
-                // Write updated content
-                let new_content = lines.join("\n");
-                if new_content != content {
-                    fs::write(config_file, new_content)
-                        .context(format!("Failed to write changes to {}", config_file.display()))?;
+            }
+        }
+        
+        let mut conflicts: Vec<String> = declared.into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .map(|((listen, name), files)| {
+                let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+                format!("server_name {} on listen {} is declared in {} server blocks: {}", 
+                        name, listen, files.len(), files.join(", "))
+            })
+            .collect();
+        conflicts.sort();
+        
+        for conflict in &conflicts {
+            warn!(service = self.service.name; "{}", conflict);
+        }
+        
+        Ok(conflicts)
+    }
+    
+    /// Get the `host:port` TLS endpoints whose certificates should be checked
+    ///
+    /// `check_cert_expiry` may be a list of targets, or `true` to discover them
+    /// from server blocks that declare an `ssl_certificate`.
+    pub fn cert_check_targets(&self) -> Result<Vec<String>> {
+        match self.service.custom_settings.get("check_cert_expiry") {
+            Some(serde_json::Value::Array(targets)) => Ok(targets.iter()
+                .filter_map(|target| target.as_str())
+                .map(String::from)
+                .collect()),
+            Some(serde_json::Value::Bool(true)) => self.discover_cert_targets(),
+            _ => Ok(vec![]),
+        }
+    }
+    
+    /// Discover TLS endpoints from the server names of SSL-enabled config files
+    fn discover_cert_targets(&self) -> Result<Vec<String>> {
+        let server_name_pattern = Regex::new(r"server_name\s+([^;]+);")?;
+        let ssl_listen_pattern = Regex::new(r"listen\s+(?:[^;]*:)?(\d+)[^;]*\bssl\b")?;
+        let mut targets = Vec::new();
+        
+        for config_file in self.find_config_files()? {
+            let content = fs::read_to_string(&config_file)
+                .context(format!("Failed to read config file: {}", config_file.display()))?;
+            
+            if !content.contains("ssl_certificate") {
+                continue;
+            }
+            
+            let port = ssl_listen_pattern.captures(&content)
+                .and_then(|cap| cap.get(1))
+                .map(|m| m.as_str().to_string())
+                .unwrap_or_else(|| "443".to_string());
+            
+            for cap in server_name_pattern.captures_iter(&content) {
+                for name in cap[1].split_whitespace() {
+                    // Skip catch-all, wildcard and regex server names
+                    if name == "_" || name.contains('*') || name.starts_with('~') {
+                        continue;
+                    }
                     
-                    info!("[{}] Added security headers to {}", self.service.name, config_file.display());
+                    let target = format!("{}:{}", name, port);
+                    if !targets.contains(&target) {
+                        targets.push(target);
+                    }
                 }
             }
         }
         
-        info!("[{}] Security enhancement complete", self.service.name);
-        Ok(())
+        Ok(targets)
+    }
+    
+    /// Check TLS certificates and report those expiring within the configured threshold
+    pub async fn check_cert_expiry(&self) -> Result<Vec<String>> {
+        let targets = self.cert_check_targets()?;
+        if targets.is_empty() {
+            return Ok(vec![]);
+        }
+        
+        let threshold_days = self.custom_settings.get("cert_expiry_threshold_days")
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_CERT_EXPIRY_THRESHOLD_DAYS as i64);
+        
+        info!(service = self.service.name; "Checking {} TLS certificates for expiry", targets.len());
+        
+        let mut warnings = Vec::new();
+        
+        for target in &targets {
+            let expires_at = match fetch_cert_expiry(target).await {
+                Ok(expires_at) => expires_at,
+                Err(e) => {
+                    warn!(service = self.service.name; "Failed to check certificate for {}: {}", target, e);
+                    continue;
+                }
+            };
+            
+            let days_left = (expires_at - Utc::now()).num_days();
+            
+            if days_left < 0 {
+                let message = format!("Certificate for {} expired on {}", target, expires_at.format("%Y-%m-%d"));
+                warn!(service = self.service.name; "{}", message);
+                warnings.push(message);
+            } else if days_left <= threshold_days {
+                let message = format!("Certificate for {} expires in {} days ({})", 
+                                      target, days_left, expires_at.format("%Y-%m-%d"));
+                warn!(service = self.service.name; "{}", message);
+                warnings.push(message);
+            }
+        }
+        
+        Ok(warnings)
     }
     
     /// Monitor Nginx logs
@@ -577,20 +886,30 @@ impl<'a> NginxService<'a> {
             return Ok(vec![]);
         }
         
-        info!("[{}] Monitoring Nginx logs", self.service.name);
+        info!(service = self.service.name; "Monitoring Nginx logs");
         
         // Convert to a simplified NginxConfig and use the shared function
         let config = NginxConfig {
             nginx_container_name: self.service.container_name.clone(),
             compose_dir: PathBuf::new(), // Not needed for log checks
-            compose_file: String::new(),  // Not needed for log checks
+            compose_files: Vec::new(),    // Not needed for log checks
+            compose_profiles: Vec::new(), // Not needed for log checks
+            env_file: None,               // Not needed for log checks
             use_docker_compose: false,    // Not needed for log checks
             disable_restart: false,       // Not needed for log checks
             monitor_logs: true,
             log_tail_lines: self.service.log_tail_lines,
+            log_error_examples: self.service.effective_log_error_examples(self.global),
+            log_streams: self.service.log_streams,
             force_rebuild: None,
+            retry_attempts: self.global.retry_attempts,
+            retry_delay: self.global.retry_delay_duration(),
+            ready_timeout: self.global.container_ready_timeout_duration(),
+            restarting_grace: self.global.restarting_grace_period_duration(),
         };
         
+        // Read before check_nginx_logs moves it, so the fallback below covers the same window
+        let since = last_log_check(&self.service.container_name)?;
         check_nginx_logs(&config).await?;
         
         // Additional detailed log analysis could be added here
@@ -600,25 +919,37 @@ impl<'a> NginxService<'a> {
         }
         
         // Get error logs
-        let output = Command::new("docker")
-            .args(["exec", &self.service.container_name, "sh", "-c", 
-                  &format!("tail -n {} /var/log/nginx/error.log", self.service.log_tail_lines)])
+        let container_name = resolve_container_name(&self.service.container_name).await?;
+        let error_log_path = self.custom_settings.get("error_log_path")
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_ERROR_LOG_PATH);
+        let tail_lines = self.service.log_tail_lines.to_string();
+        let output = container_command()
+            .args(["exec", &container_name, "tail", "-n", &tail_lines, error_log_path])
             .output()
             .await
             .context("Failed to get Nginx error logs")?;
         
-        if !output.status.success() {
-            warn!("[{}] Failed to retrieve Nginx error logs", self.service.name);
+        let logs: Vec<String> = if output.status.success() {
+            String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect()
+        } else if String::from_utf8_lossy(&output.stderr).contains("No such file") {
+            // Images that log to stdout/stderr have no error log file; read the container output instead
+            debug!(service = self.service.name; "{} not found in the container, reading the container logs", 
+                                                error_log_path);
+            get_container_logs(&self.service.container_name, self.service.log_tail_lines, since, self.service.log_streams).await?
+                .lines()
+                .map(|(stream, line)| format!("{}: {}", stream, line))
+                .collect()
+        } else {
+            warn!(service = self.service.name; "Failed to retrieve Nginx error logs");
             return Ok(vec![]);
-        }
+        };
         
-        let logs = String::from_utf8_lossy(&output.stdout);
-        let log_lines: Vec<String> = logs.lines()
+        let log_lines: Vec<String> = logs.into_iter()
             .filter(|line| {
                 line.contains("error") || line.contains("critical") || 
                 line.contains("alert") || line.contains("emerg")
             })
-            .map(String::from)
             .collect();
         
         Ok(log_lines)
@@ -631,6 +962,18 @@ pub async fn validate_nginx(service: &ServiceConfig, global: &GlobalSettings) ->
     nginx.validate_config().await
 }
 
+/// Check TLS certificate expiry - standalone function for external use
+pub async fn check_cert_expiry(service: &ServiceConfig, global: &GlobalSettings) -> Result<Vec<String>> {
+    let nginx = NginxService::new(service, global)?;
+    nginx.check_cert_expiry().await
+}
+
+/// Check for conflicting server blocks - standalone function for external use
+pub fn check_server_conflicts(service: &ServiceConfig, global: &GlobalSettings) -> Result<Vec<String>> {
+    let nginx = NginxService::new(service, global)?;
+    nginx.check_server_conflicts()
+}
+
 /// Fix common Nginx issues - standalone function for external use 
 pub async fn fix_issues(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
     let nginx = NginxService::new(service, global)?;
@@ -644,22 +987,209 @@ pub async fn fix_nginx_permissions(service: &ServiceConfig, global: &GlobalSetti
     nginx.fix_permissions().await
 }
 
-/// ServiceHandler trait for polymorphic service operations
-#[async_trait]
-pub trait ServiceHandler {
-    async fn validate(&self) -> Result<bool>;
-    async fn fix_issues(&self) -> Result<()>;
-    async fn fix_permissions(&self) -> Result<()>;
-    async fn monitor(&self) -> Result<Vec<String>>;
+/// Split a cert check target into the host name and the `host:port` to connect to (port 443 by default)
+///
+/// IPv6 addresses may be given bare (`::1`) or bracketed with an optional port (`[::1]:8443`).
+fn split_cert_target(target: &str) -> (&str, String) {
+    if let Some(rest) = target.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once(']') {
+            let port = port.strip_prefix(':').unwrap_or("443");
+            return (host, format!("[{}]:{}", host, port));
+        }
+    }
+    
+    match target.matches(':').count() {
+        0 => (target, format!("{}:443", target)),
+        1 => (target.split_once(':').map_or(target, |(host, _)| host), target.to_string()),
+        _ => (target, format!("[{}]:443", target)),
+    }
+}
+
+/// Fetch the expiry date of the certificate served at a `host:port` target
+async fn fetch_cert_expiry(target: &str) -> Result<DateTime<Utc>> {
+    let (host, connect) = split_cert_target(target);
+    
+    // Retrieve the certificate presented by the server
+    let output = Command::new("openssl")
+        .args(["s_client", "-connect", &connect, "-servername", host])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Failed to execute openssl s_client")?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("TLS connection to {} failed: {}", connect, stderr.trim()));
+    }
+    
+    // Extract the expiry date from the certificate
+    let mut x509 = Command::new("openssl")
+        .args(["x509", "-noout", "-enddate"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to execute openssl x509")?;
+    
+    if let Some(mut stdin) = x509.stdin.take() {
+        stdin.write_all(&output.stdout).await
+            .context("Failed to pass certificate to openssl x509")?;
+    }
+    
+    let output = x509.wait_with_output().await
+        .context("Failed to read openssl x509 output")?;
+    
+    if !output.status.success() {
+        return Err(anyhow!("No certificate returned by {}", connect));
+    }
+    
+    parse_cert_end_date(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the `notAfter=...` line printed by `openssl x509 -enddate`
+fn parse_cert_end_date(output: &str) -> Result<DateTime<Utc>> {
+    let value = output.trim()
+        .strip_prefix("notAfter=")
+        .ok_or_else(|| anyhow!("Unexpected openssl output: {}", output.trim()))?;
+    
+    // Collapse the double space openssl uses to pad single-digit days
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    
+    let date = NaiveDateTime::parse_from_str(&value, "%b %d %H:%M:%S %Y GMT")
+        .context(format!("Failed to parse certificate expiry date: {}", value))?;
+    
+    Ok(date.and_utc())
+}
+
+/// The `listen` and `server_name` values declared by a single server block
+#[derive(Debug, Default)]
+struct ServerBlock {
+    listens: Vec<String>,
+    server_names: Vec<String>,
+}
+
+/// Extract the server blocks from an Nginx config file
+fn parse_server_blocks(content: &str) -> Vec<ServerBlock> {
+    // Drop comments so commented-out directives and braces are ignored
+    let content: String = content.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let server_pattern = Regex::new(r"\bserver\s*\{").unwrap();
+    let listen_pattern = Regex::new(r"\blisten\s+([^\s;]+)").unwrap();
+    let server_name_pattern = Regex::new(r"\bserver_name\s+([^;]+);").unwrap();
+    
+    let mut blocks = Vec::new();
+    
+    for start in server_pattern.find_iter(&content) {
+        // Find the matching closing brace
+        let mut depth = 0;
+        let mut end = content.len();
+        for (i, c) in content[start.end() - 1..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = start.end() - 1 + i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let body = &content[start.end()..end];
+        
+        let mut block = ServerBlock::default();
+        for cap in listen_pattern.captures_iter(body) {
+            block.listens.push(cap[1].to_string());
+        }
+        for cap in server_name_pattern.captures_iter(body) {
+            block.server_names.extend(cap[1].split_whitespace().map(String::from));
+        }
+        
+        // Nginx listens on port 80 when a server block has no listen directive
+        if block.listens.is_empty() {
+            block.listens.push("80".to_string());
+        }
+        
+        blocks.push(block);
+    }
+    
+    blocks
+}
+
+/// Add the headers missing from the first location block of each server block
+fn insert_security_headers(content: &str, headers: &[(String, String)]) -> String {
+    let server_pattern = Regex::new(r"^\s*server\s*\{").unwrap();
+    let location_pattern = Regex::new(r"^\s*location\b.*\{").unwrap();
+    
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let mut in_server = false;
+    let mut i = 0;
+    
+    while i < lines.len() {
+        if server_pattern.is_match(&lines[i]) {
+            in_server = true;
+        } else if in_server && location_pattern.is_match(&lines[i]) {
+            in_server = false;
+            
+            // Find the end of the location block to see which headers it already sets
+            let mut depth = 0;
+            let mut end = lines.len();
+            for (j, line) in lines.iter().enumerate().skip(i) {
+                let code = line.split('#').next().unwrap_or("");
+                depth += code.matches('{').count() as i32 - code.matches('}').count() as i32;
+                if depth <= 0 {
+                    end = j;
+                    break;
+                }
+            }
+            let block = lines[i..end].join("\n").to_lowercase();
+            
+            let indent = lines[i].len() - lines[i].trim_start().len();
+            let missing: Vec<String> = headers.iter()
+                .filter(|(name, _)| !block.contains(&format!("add_header {}", name.to_lowercase())))
+                .map(|(name, value)| format!("{:indent$}add_header {} \"{}\";", "", name, value.replace('"', "\\\""), indent = indent + 4))
+                .collect();
+            
+            if !missing.is_empty() {
+                let count = missing.len() + 1;
+                let comment = format!("{:indent$}# Security headers", "", indent = indent + 4);
+                lines.splice(i + 1..i + 1, std::iter::once(comment).chain(missing));
+                i += count;
+            }
+        }
+        
+        i += 1;
+    }
+    
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
 }
 
 /// Implement the ServiceHandler trait for NginxService
 #[async_trait]
 impl<'a> ServiceHandler for NginxService<'a> {
+    fn name(&self) -> &str {
+        &self.service.name
+    }
+    
+    fn service_type(&self) -> ServiceType {
+        ServiceType::Nginx
+    }
+    
     async fn validate(&self) -> Result<bool> {
         self.validate_config().await
     }
     
+    async fn restart(&self) -> Result<()> {
+        restart_service(self.service, self.global).await
+    }
+    
     async fn fix_issues(&self) -> Result<()> {
         self.fix_common_issues().await?;
         self.enhance_security().await
@@ -672,18 +1202,88 @@ impl<'a> ServiceHandler for NginxService<'a> {
     async fn monitor(&self) -> Result<Vec<String>> {
         self.monitor_logs().await
     }
+    
+    async fn health(&self) -> Result<bool> {
+        check_health(self.service, self.global).await
+    }
 }
 
-/// Create a service handler factory based on service type
-pub fn create_service_handler<'a>(
-    service: &'a ServiceConfig, 
-    global: &'a GlobalSettings
-) -> Result<Box<dyn ServiceHandler + 'a>> {
-    match service.service_type {
-        ServiceType::Nginx => {
-            let nginx = NginxService::new(service, global)?;
-            Ok(Box::new(nginx))
-        },
-        _ => Err(anyhow!("Service type not supported yet: {:?}", service.service_type)),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_cert_end_date() {
+        let date = parse_cert_end_date("notAfter=Mar  5 12:30:00 2031 GMT\n").unwrap();
+        assert_eq!(date.format("%Y-%m-%d %H:%M:%S").to_string(), "2031-03-05 12:30:00");
+        
+        let date = parse_cert_end_date("notAfter=Nov 17 08:00:00 2030 GMT").unwrap();
+        assert_eq!(date.format("%Y-%m-%d").to_string(), "2030-11-17");
+        
+        assert!(parse_cert_end_date("unable to load certificate").is_err());
+    }
+    
+    #[test]
+    fn test_split_cert_target() {
+        assert_eq!(split_cert_target("example.com"), ("example.com", "example.com:443".to_string()));
+        assert_eq!(split_cert_target("example.com:8443"), ("example.com", "example.com:8443".to_string()));
+        assert_eq!(split_cert_target("2001:db8::1"), ("2001:db8::1", "[2001:db8::1]:443".to_string()));
+        assert_eq!(split_cert_target("[2001:db8::1]:8443"), ("2001:db8::1", "[2001:db8::1]:8443".to_string()));
+        assert_eq!(split_cert_target("[::1]"), ("::1", "[::1]:443".to_string()));
+    }
+    
+    #[test]
+    fn test_group_log_errors() {
+        let errors = vec![
+            "2024/05/01 10:00:00 [error] 29#29: *101 connect() failed (111: Connection refused) while connecting to upstream, client: 10.0.0.1",
+            "2024/05/01 10:00:05 [error] 29#29: *102 open() \"/usr/share/nginx/html/favicon.ico\" failed (2: No such file or directory)",
+            "2024/05/01 10:00:07 [error] 30#30: *188 connect() failed (111: Connection refused) while connecting to upstream, client: 10.0.0.2",
+        ];
+        
+        let grouped = group_log_errors(&errors);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].1, 2);
+        assert!(grouped[0].0.starts_with("[error] connect() failed"));
+        assert_eq!(grouped[1].1, 1);
+    }
+    
+    #[test]
+    fn test_parse_server_blocks() {
+        let content = r#"
+            server {
+                listen 443 ssl;
+                listen [::]:443 ssl;
+                server_name example.com www.example.com;
+                location / {
+                    root /usr/share/nginx/html;
+                }
+            }
+            # server { listen 8080; server_name commented.example.com; }
+            server {
+                server_name other.example.com;
+            }
+        "#;
+        
+        let blocks = parse_server_blocks(content);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].listens, vec!["443", "[::]:443"]);
+        assert_eq!(blocks[0].server_names, vec!["example.com", "www.example.com"]);
+        assert_eq!(blocks[1].listens, vec!["80"]);
+        assert_eq!(blocks[1].server_names, vec!["other.example.com"]);
+    }
+    
+    #[test]
+    fn test_insert_security_headers() {
+        let content = "server {\n    listen 80;\n    location / {\n        add_header X-Frame-Options DENY;\n    }\n    location /api {\n    }\n}\n";
+        let headers = vec![
+            ("X-Frame-Options".to_string(), "SAMEORIGIN".to_string()),
+            ("Strict-Transport-Security".to_string(), "max-age=63072000".to_string()),
+        ];
+        
+        let updated = insert_security_headers(content, &headers);
+        assert_eq!(updated, "server {\n    listen 80;\n    location / {\n        # Security headers\n        add_header Strict-Transport-Security \"max-age=63072000\";\n        add_header X-Frame-Options DENY;\n    }\n    location /api {\n    }\n}\n");
+        
+        // Running again changes nothing
+        assert_eq!(insert_security_headers(&updated, &headers), updated);
     }
 }
\ No newline at end of file
@@ -0,0 +1,323 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::time::Duration;
+
+use crate::config::{Config, NotifierConfig};
+use crate::logger::{rate_limit, HealthcheckClient};
+
+/// Timeout for requests to notification endpoints
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notifiers every event is sent to, set from the configuration
+static NOTIFIERS: LazyLock<Mutex<Vec<Arc<dyn Notifier>>>> =
+    LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// What happened to a service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// No check completed for several intervals
+    Stale,
+    /// Checks succeed again after a failure streak
+    Recovered,
+    /// Updates are paused after repeated validation failures
+    CircuitOpen,
+    /// A branch matching `notify_branches` got new commits
+    BranchAdvanced { branch: String, commit: String },
+    /// The remote of a read-only service is ahead of its checkout
+    Drift { repo_url: String, local: String, remote: String },
+}
+
+impl EventKind {
+    /// Short name of the event kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Stale => "stale",
+            EventKind::Recovered => "recovered",
+            EventKind::CircuitOpen => "circuit_open",
+            EventKind::BranchAdvanced { .. } => "branch_advanced",
+            EventKind::Drift { .. } => "drift",
+        }
+    }
+
+    /// Whether the event reports a problem
+    pub fn is_error(&self) -> bool {
+        matches!(self, EventKind::Stale | EventKind::CircuitOpen | EventKind::Drift { .. })
+    }
+
+    /// Key repeated events are rate limited by, per service
+    fn rate_limit_key(&self) -> String {
+        match self {
+            EventKind::BranchAdvanced { branch, .. } => format!("{}:{}", self.name(), branch),
+            EventKind::Drift { repo_url, .. } => format!("{}:{}", self.name(), repo_url),
+            _ => self.name().to_string(),
+        }
+    }
+}
+
+/// An event reported to the notifiers
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherEvent {
+    pub service: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+    pub message: String,
+    /// RFC 3339 time the event happened
+    pub timestamp: String,
+}
+
+impl WatcherEvent {
+    /// Create an event for a service, timestamped now
+    pub fn new(service: &str, kind: EventKind, message: String) -> Self {
+        Self {
+            service: service.to_string(),
+            kind,
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// A channel events are sent to
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Name of the channel, for error messages
+    fn name(&self) -> &str;
+
+    /// Whether repeated events of the same kind are suppressed within the notification window
+    fn rate_limited(&self) -> bool {
+        true
+    }
+
+    /// Send an event
+    async fn notify(&self, event: &WatcherEvent) -> Result<()>;
+}
+
+/// Writes events to the log
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn rate_limited(&self) -> bool {
+        false
+    }
+
+    async fn notify(&self, event: &WatcherEvent) -> Result<()> {
+        match event.kind {
+            EventKind::CircuitOpen => error!(service = event.service; "{}", event.message),
+            EventKind::Stale | EventKind::Drift { .. } => warn!(service = event.service; "{}", event.message),
+            _ => info!(service = event.service; "{}", event.message),
+        }
+        Ok(())
+    }
+}
+
+/// Pings the `healthcheck_url` of the service an event is about
+pub struct HealthcheckNotifier {
+    client: HealthcheckClient,
+}
+
+impl HealthcheckNotifier {
+    /// Create from the services' healthcheck URLs and the global attempts and timeout
+    pub fn new(config: &Config) -> Self {
+        let global = &config.global_settings;
+        let mut client = HealthcheckClient::new(global.healthcheck_timeout_duration())
+            .with_attempts(global.healthcheck_attempts);
+        
+        for service in &config.services {
+            if let Some(url) = &service.healthcheck_url {
+                client.add_service(&service.name, url);
+            }
+        }
+        
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Notifier for HealthcheckNotifier {
+    fn name(&self) -> &str {
+        "healthcheck"
+    }
+
+    async fn notify(&self, event: &WatcherEvent) -> Result<()> {
+        self.client.notify(&event.service, &event.message, event.kind.is_error()).await
+    }
+}
+
+/// Posts events to a Slack incoming webhook
+pub struct SlackWebhookNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackWebhookNotifier {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            webhook_url: webhook_url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackWebhookNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn notify(&self, event: &WatcherEvent) -> Result<()> {
+        let icon = if event.kind.is_error() { ":red_circle:" } else { ":large_green_circle:" };
+        let body = serde_json::json!({
+            "text": format!("{} [{}] {}", icon, event.service, event.message),
+        });
+        
+        post_json(&self.client, &self.webhook_url, &body).await
+    }
+}
+
+/// Posts every event as JSON to an arbitrary URL
+pub struct GenericWebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl GenericWebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for GenericWebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn notify(&self, event: &WatcherEvent) -> Result<()> {
+        post_json(&self.client, &self.url, event).await
+    }
+}
+
+/// POST a JSON body, failing on a non-success status
+async fn post_json<T: Serialize + ?Sized>(client: &reqwest::Client, url: &str, body: &T) -> Result<()> {
+    let response = client.post(url)
+        .json(body)
+        .timeout(NOTIFY_TIMEOUT)
+        .send()
+        .await
+        .context(format!("Failed to send notification to {}", url))?;
+    
+    if !response.status().is_success() {
+        return Err(anyhow!("Notification endpoint returned status {}", response.status()));
+    }
+    
+    Ok(())
+}
+
+/// Build the notifiers for a configuration: the log, healthcheck URLs and the configured channels
+pub fn notifiers_from_config(config: &Config) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![
+        Box::new(LogNotifier),
+        Box::new(HealthcheckNotifier::new(config)),
+    ];
+    
+    for notifier in &config.global_settings.notifiers {
+        match notifier {
+            NotifierConfig::Slack { webhook_url } => notifiers.push(Box::new(SlackWebhookNotifier::new(webhook_url))),
+            NotifierConfig::Webhook { url } => notifiers.push(Box::new(GenericWebhookNotifier::new(url))),
+        }
+    }
+    
+    notifiers
+}
+
+/// Replace the notifiers events are sent to
+pub fn set_notifiers(notifiers: Vec<Box<dyn Notifier>>) {
+    if let Ok(mut current) = NOTIFIERS.lock() {
+        *current = notifiers.into_iter().map(Arc::from).collect();
+    }
+}
+
+/// Send an event to every notifier, suppressing repeats of it within the window on rate-limited ones
+pub async fn dispatch(event: WatcherEvent, window: Duration) {
+    let notifiers: Vec<Arc<dyn Notifier>> = match NOTIFIERS.lock() {
+        Ok(notifiers) => notifiers.clone(),
+        Err(_) => return,
+    };
+    
+    let suppressed = match rate_limit(&event.service, &event.kind.rate_limit_key(), window) {
+        Ok(suppressed) => suppressed,
+        Err(e) => {
+            warn!(service = event.service; "{}", e);
+            Some(0)
+        }
+    };
+    
+    // Report how many repeats were held back with the next event that gets through
+    let limited_event = suppressed.map(|suppressed| match suppressed {
+        0 => event.clone(),
+        n => WatcherEvent {
+            message: format!("{} ({} similar notifications suppressed)", event.message, n),
+            ..event.clone()
+        },
+    });
+    
+    for notifier in notifiers {
+        let event = match (notifier.rate_limited(), &limited_event) {
+            (false, _) => &event,
+            (true, Some(limited_event)) => limited_event,
+            (true, None) => continue,
+        };
+        
+        if let Err(e) = notifier.notify(event).await {
+            warn!(service = event.service; "Failed to send {} notification via {}: {}",
+                                           event.kind.name(), notifier.name(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Records the messages of the events it receives
+    struct RecordingNotifier(Arc<Mutex<Vec<String>>>);
+    
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &str {
+            "recording"
+        }
+        
+        async fn notify(&self, event: &WatcherEvent) -> Result<()> {
+            self.0.lock().unwrap().push(event.message.clone());
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_dispatch_rate_limits_repeated_events() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        set_notifiers(vec![Box::new(RecordingNotifier(received.clone()))]);
+        
+        let window = Duration::from_secs(60);
+        for _ in 0..3 {
+            dispatch(WatcherEvent::new("notifier-test", EventKind::Stale, "stuck".to_string()), window).await;
+        }
+        dispatch(WatcherEvent::new("notifier-test", EventKind::Recovered, "recovered".to_string()), window).await;
+        
+        assert_eq!(*received.lock().unwrap(), vec!["stuck", "recovered"]);
+    }
+}
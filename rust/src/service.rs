@@ -1,29 +1,45 @@
 use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
 use log::{debug, error, info, warn};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 use tokio::process::Command;
 use tokio::time::timeout;
 
 use crate::config::{GlobalSettings, ServiceConfig, ServiceType};
+use crate::apache::ApacheService;
+use crate::custom_service::CustomCommandService;
 use crate::docker_utils::{
-    ContainerStatus, DockerComposeConfig, check_container_status, restart_container,
+    ContainerStatus, DockerComposeConfig, check_container_status, container_command, get_container_health, get_container_networks, get_container_resource_usage, resolve_container_name, tail_container_file, restart_container, wait_while_restarting,
     restart_with_docker_compose, recreate_with_docker_compose
 };
+use crate::git::service as git_service;
+use crate::kubernetes_utils::{check_deployment_status, rollout_restart};
+use crate::error::WatcherError;
+use crate::nginx::NginxService;
+use crate::utils::{fix_permissions, unprivileged_command};
 
 /// Default command timeout in seconds
 const DEFAULT_COMMAND_TIMEOUT: u64 = 60;
 
+/// Timeout for requests to a service's `health_url`
+const HEALTH_URL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Commit that last failed validation per service, cleared when a validation passes
+static INVALID_COMMITS: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// Run validation command for a service
 pub async fn run_validation(service: &ServiceConfig, validation_cmd: &str) -> Result<()> {
-    info!("[{}] Running validation command: {}", service.name, validation_cmd);
+    info!(service = service.name; "Running validation command: {}", validation_cmd);
     
+    let mut command = validation_command(service, validation_cmd).await?;
     let result = timeout(
         Duration::from_secs(DEFAULT_COMMAND_TIMEOUT),
-        Command::new("sh")
-            .arg("-c")
-            .arg(validation_cmd)
-            .output()
+        command.output()
     ).await
         .context("Validation command timed out")?
         .context(format!("Failed to execute validation command for service {}", service.name))?;
@@ -32,19 +48,141 @@ pub async fn run_validation(service: &ServiceConfig, validation_cmd: &str) -> Re
         let stderr = String::from_utf8_lossy(&result.stderr);
         let stdout = String::from_utf8_lossy(&result.stdout);
         
-        error!("[{}] Validation failed with exit code: {:?}", service.name, result.status.code());
+        error!(service = service.name; "Validation failed with exit code: {:?}", result.status.code());
         if !stderr.is_empty() {
-            error!("[{}] Validation error output: {}", service.name, stderr);
+            error!(service = service.name; "Validation error output: {}", stderr);
         }
         if !stdout.is_empty() {
-            debug!("[{}] Validation output: {}", service.name, stdout);
+            debug!(service = service.name; "Validation output: {}", stdout);
         }
         
         return Err(anyhow!("Validation command failed for service {} with exit code: {:?}",
                            service.name, result.status.code()));
     }
     
-    info!("[{}] Validation successful", service.name);
+    info!(service = service.name; "Validation successful");
+    Ok(())
+}
+
+/// Run a validation command against a staged checkout instead of the service's live tree
+pub async fn run_staged_validation(service: &ServiceConfig, validation_cmd: &str, staged: &Path) -> Result<()> {
+    let mut staged_service = service.clone();
+    
+    // Keep a working directory inside the checkout at the same place within the staged one
+    staged_service.command_cwd = Some(match &service.command_cwd {
+        Some(cwd) => cwd.strip_prefix(&service.local_path)
+            .map(|relative| staged.join(relative))
+            .unwrap_or_else(|_| cwd.clone()),
+        None => staged.to_path_buf(),
+    });
+    staged_service.local_path = staged.to_path_buf();
+    
+    run_validation(&staged_service, validation_cmd).await
+}
+
+/// Remember the checked-out commit as invalid after its validation failed
+pub async fn record_validation_failure(service: &ServiceConfig, global: &GlobalSettings) {
+    match git_service::current_commit(service, global).await {
+        Ok(Some(commit)) => {
+            if let Ok(mut invalid) = INVALID_COMMITS.lock() {
+                invalid.insert(service.name.clone(), commit);
+            }
+        },
+        Ok(None) => {},
+        Err(e) => warn!(service = service.name; "Failed to read the commit that failed validation: {}", e),
+    }
+}
+
+/// Check whether an error is an update rejected by validation, as opposed to a transient failure
+pub fn is_validation_error(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<WatcherError>(), Some(WatcherError::ValidationFailed { .. }))
+}
+
+/// Forget a validation failure once the service's config validates again
+pub fn clear_validation_failure(service: &ServiceConfig) {
+    if let Ok(mut invalid) = INVALID_COMMITS.lock() {
+        invalid.remove(&service.name);
+    }
+}
+
+/// Refuse to start a stopped container on a checkout that failed validation, if the service asks for it
+pub async fn ensure_startable(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    if !service.keep_stopped_on_invalid {
+        return Ok(());
+    }
+    
+    let invalid = match INVALID_COMMITS.lock() {
+        Ok(invalid) => invalid.get(&service.name).cloned(),
+        Err(_) => None,
+    };
+    let invalid = match invalid {
+        Some(commit) => commit,
+        None => return Ok(()),
+    };
+    
+    if git_service::current_commit(service, global).await?.as_deref() != Some(invalid.as_str()) {
+        return Ok(());
+    }
+    
+    if check_service_status(service).await? == ContainerStatus::Running {
+        return Ok(());
+    }
+    
+    Err(anyhow!("Not starting service {}: checked-out commit {} failed validation", service.name, invalid))
+}
+
+/// Build a `sh -c` command for a service's user-defined command, running in its
+/// `command_cwd` (default `local_path`) with its `command_env` set
+pub fn shell_command(service: &ServiceConfig, cmd: &str) -> Command {
+    let mut command = unprivileged_command("sh");
+    command.args(["-c", cmd])
+        .current_dir(service.command_cwd.as_ref().unwrap_or(&service.local_path))
+        .envs(&service.command_env);
+    command
+}
+
+/// Build the command running a validation command on the host, or inside the
+/// service's container when `validation_in_container` is set
+async fn validation_command(service: &ServiceConfig, validation_cmd: &str) -> Result<Command> {
+    if !service.validation_in_container {
+        return Ok(shell_command(service, validation_cmd));
+    }
+    
+    let mut command = if service.service_type == ServiceType::Kubernetes {
+        let target = format!("deployment/{}", service.effective_deployment());
+        let mut command = Command::new("kubectl");
+        command.args(["exec", &target, "-n", &service.effective_namespace(), "--"]);
+        command
+    } else {
+        let container_name = resolve_container_name(&service.container_name).await?;
+        let mut command = container_command();
+        command.args(["exec", &container_name]);
+        command
+    };
+    
+    command.args(["sh", "-c", validation_cmd]);
+    Ok(command)
+}
+
+/// Run the pre-apply hook for a service, if one is configured
+pub async fn run_pre_apply_hook(service: &ServiceConfig) -> Result<()> {
+    if let Some(cmd) = &service.pre_apply_command {
+        info!(service = service.name; "Running pre-apply command: {}", cmd);
+        execute_custom_command(cmd, service).await
+            .context(format!("Pre-apply command failed for service {}", service.name))?;
+    }
+    
+    Ok(())
+}
+
+/// Run the post-apply hook for a service, if one is configured
+pub async fn run_post_apply_hook(service: &ServiceConfig) -> Result<()> {
+    if let Some(cmd) = &service.post_apply_command {
+        info!(service = service.name; "Running post-apply command: {}", cmd);
+        execute_custom_command(cmd, service).await
+            .context(format!("Post-apply command failed for service {}", service.name))?;
+    }
+    
     Ok(())
 }
 
@@ -52,17 +190,22 @@ pub async fn run_validation(service: &ServiceConfig, validation_cmd: &str) -> Re
 pub async fn restart_service(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
     // Skip if restart is disabled
     if service.disable_restart || global.disable_restart {
-        info!("[{}] Service restart is disabled by configuration. Skipping.", service.name);
+        info!(service = service.name; "Service restart is disabled by configuration. Skipping.");
         return Ok(());
     }
     
-    info!("[{}] Restarting service", service.name);
+    ensure_startable(service, global).await?;
+    
+    info!(service = service.name; "Restarting service");
     
     // Use the appropriate restart method based on service type and configuration
     match service.service_type {
         ServiceType::Nginx | ServiceType::Apache => {
             restart_web_service(service, global).await
         },
+        ServiceType::Kubernetes => {
+            restart_kubernetes_service(service).await
+        },
         _ => {
             restart_generic_service(service, global).await
         }
@@ -73,7 +216,7 @@ pub async fn restart_service(service: &ServiceConfig, global: &GlobalSettings) -
 async fn restart_web_service(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
     // Check if we should use a custom restart command
     if let Some(cmd) = &service.restart_command {
-        info!("[{}] Using custom restart command: {}", service.name, cmd);
+        info!(service = service.name; "Using custom restart command: {}", cmd);
         return execute_custom_command(cmd, service).await;
     }
     
@@ -86,15 +229,19 @@ async fn restart_web_service(service: &ServiceConfig, global: &GlobalSettings) -
     } else {
         match status {
             ContainerStatus::Running => {
-                info!("[{}] Restarting running container", service.name);
-                restart_container(&service.container_name).await
+                info!(service = service.name; "Restarting running container");
+                restart_container(&service.container_name, global.retry_attempts, global.retry_delay_duration(), global.restarting_grace_period_duration()).await
+            },
+            ContainerStatus::Restarting => {
+                info!(service = service.name; "Container is restarting, waiting for it to settle");
+                restart_container(&service.container_name, global.retry_attempts, global.retry_delay_duration(), global.restarting_grace_period_duration()).await
             },
             ContainerStatus::Stopped => {
-                info!("[{}] Starting stopped container", service.name);
-                restart_container(&service.container_name).await
+                info!(service = service.name; "Starting stopped container");
+                restart_container(&service.container_name, global.retry_attempts, global.retry_delay_duration(), global.restarting_grace_period_duration()).await
             },
             ContainerStatus::NotExists => {
-                error!("[{}] Container does not exist", service.name);
+                error!(service = service.name; "Container does not exist");
                 Err(anyhow!("Container {} does not exist and cannot be restarted", 
                            service.container_name))
             }
@@ -105,9 +252,9 @@ async fn restart_web_service(service: &ServiceConfig, global: &GlobalSettings) -
 /// Restart a generic service
 async fn restart_generic_service(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
     // Check if we should use a custom restart command
-    if let Some(cmd) = &service.restart_command {
-        info!("[{}] Using custom restart command: {}", service.name, cmd);
-        return execute_custom_command(cmd, service).await;
+    if let Some(cmd) = service.effective_restart_command(global) {
+        info!(service = service.name; "Using custom restart command: {}", cmd);
+        return execute_custom_command(&cmd, service).await;
     }
     
     // Otherwise use Docker or Docker Compose based on config
@@ -115,18 +262,34 @@ async fn restart_generic_service(service: &ServiceConfig, global: &GlobalSetting
         let status = check_service_status(service).await?;
         restart_with_compose(service, global, status).await
     } else {
-        restart_container(&service.container_name).await
+        restart_container(&service.container_name, global.retry_attempts, global.retry_delay_duration(), global.restarting_grace_period_duration()).await
     }
 }
 
+/// Restart a Kubernetes deployment with a rollout restart
+async fn restart_kubernetes_service(service: &ServiceConfig) -> Result<()> {
+    // Check if we should use a custom restart command
+    if let Some(cmd) = &service.restart_command {
+        info!(service = service.name; "Using custom restart command: {}", cmd);
+        return execute_custom_command(cmd, service).await;
+    }
+    
+    let namespace = service.effective_namespace();
+    let deployment = service.effective_deployment();
+    
+    if check_service_status(service).await? == ContainerStatus::NotExists {
+        return Err(anyhow!("Deployment {} does not exist in namespace {}", deployment, namespace));
+    }
+    
+    info!(service = service.name; "Restarting deployment {} in namespace {}", deployment, namespace);
+    rollout_restart(&namespace, &deployment, Duration::from_secs(DEFAULT_COMMAND_TIMEOUT)).await
+}
+
 /// Execute a custom shell command
 async fn execute_custom_command(cmd: &str, service: &ServiceConfig) -> Result<()> {
     let result = timeout(
         Duration::from_secs(DEFAULT_COMMAND_TIMEOUT),
-        Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .output()
+        shell_command(service, cmd).output()
     ).await
         .context("Custom command timed out")?
         .context(format!("Failed to execute custom command for service {}", service.name))?;
@@ -135,19 +298,19 @@ async fn execute_custom_command(cmd: &str, service: &ServiceConfig) -> Result<()
         let stderr = String::from_utf8_lossy(&result.stderr);
         let stdout = String::from_utf8_lossy(&result.stdout);
         
-        error!("[{}] Custom command failed with exit code: {:?}", service.name, result.status.code());
+        error!(service = service.name; "Custom command failed with exit code: {:?}", result.status.code());
         if !stderr.is_empty() {
-            error!("[{}] Command error output: {}", service.name, stderr);
+            error!(service = service.name; "Command error output: {}", stderr);
         }
         if !stdout.is_empty() {
-            debug!("[{}] Command output: {}", service.name, stdout);
+            debug!(service = service.name; "Command output: {}", stdout);
         }
         
         return Err(anyhow!("Custom command failed for service {} with exit code: {:?}",
                            service.name, result.status.code()));
     }
     
-    info!("[{}] Custom command executed successfully", service.name);
+    info!(service = service.name; "Custom command executed successfully");
     Ok(())
 }
 
@@ -160,24 +323,35 @@ async fn restart_with_compose(
     // Determine compose directory
     let compose_dir = resolve_compose_directory(service, global)?;
     
-    // Get compose file
-    let compose_file = service.docker_compose_file.clone()
-        .or_else(|| global.default_compose_file.clone());
+    // Get compose files
+    let compose_files = service.get_compose_files(&global.default_compose_file);
     
     // Build the compose config
     let compose_config = DockerComposeConfig {
         compose_dir,
-        compose_file,
+        compose_files,
+        compose_profiles: service.compose_profiles.clone(),
+        env_file: service.env_file.clone(),
+        ready_timeout: global.container_ready_timeout_duration(),
         service_name: service.container_name.clone(),
     };
     
+    // Let a restart already in progress finish rather than racing it
+    let status = match status {
+        ContainerStatus::Restarting => {
+            info!(service = service.name; "Container is restarting, waiting for it to settle");
+            wait_while_restarting(&service.container_name, global.restarting_grace_period_duration()).await?
+        },
+        status => status,
+    };
+    
     match status {
         ContainerStatus::NotExists => {
-            info!("[{}] Container does not exist, recreating with docker-compose", service.name);
+            info!(service = service.name; "Container does not exist, recreating with docker-compose");
             recreate_with_docker_compose(&compose_config).await
         },
         _ => {
-            info!("[{}] Restarting with docker-compose", service.name);
+            info!(service = service.name; "Restarting with docker-compose");
             restart_with_docker_compose(&compose_config).await
         }
     }
@@ -196,8 +370,9 @@ fn resolve_compose_directory(service: &ServiceConfig, global: &GlobalSettings) -
     }
     
     // Use service path if available
-    if service.local_path.exists() && service.local_path.is_dir() {
-        return Ok(service.local_path.clone());
+    let config_dir = service.config_dir();
+    if config_dir.is_dir() {
+        return Ok(config_dir);
     }
     
     // Fall back to current directory
@@ -207,26 +382,113 @@ fn resolve_compose_directory(service: &ServiceConfig, global: &GlobalSettings) -
 
 /// Check if a service container exists and is running
 pub async fn check_service_status(service: &ServiceConfig) -> Result<ContainerStatus> {
-    debug!("[{}] Checking container status", service.name);
+    if service.service_type == ServiceType::Kubernetes {
+        debug!(service = service.name; "Checking deployment rollout status");
+        return check_deployment_status(&service.effective_namespace(), &service.effective_deployment()).await;
+    }
+    
+    debug!(service = service.name; "Checking container status");
     check_container_status(&service.container_name).await
 }
 
+/// Check the container's CPU and memory usage against the service's thresholds,
+/// returning a warning for each one exceeded
+pub async fn check_resource_usage(service: &ServiceConfig) -> Result<Vec<String>> {
+    // Deployments are spread over pods that docker stats cannot see
+    if service.service_type == ServiceType::Kubernetes {
+        return Ok(vec![]);
+    }
+    
+    let usage = get_container_resource_usage(&service.container_name).await?;
+    debug!(service = service.name; "CPU {:.1}%, memory {:.1}%", usage.cpu_percent, usage.memory_percent);
+    
+    let mut warnings = Vec::new();
+    if usage.cpu_percent > service.max_cpu_percent {
+        warnings.push(format!("CPU usage {:.1}% exceeds the {}% threshold", usage.cpu_percent, service.max_cpu_percent));
+    }
+    if usage.memory_percent > service.max_memory_percent {
+        warnings.push(format!("Memory usage {:.1}% exceeds the {}% threshold", usage.memory_percent, service.max_memory_percent));
+    }
+    
+    Ok(warnings)
+}
+
+/// Runtime snapshot of a single service
+#[derive(Debug)]
+pub struct ServiceStatus {
+    pub name: String,
+    /// Commit checked out in `local_path`, or None if the repository has not been cloned
+    pub commit: Option<String>,
+    pub container: Result<ContainerStatus>,
+    /// Whether the service's handler reports it healthy right now
+    pub healthy: Result<bool>,
+    /// Last modification time of `local_path`
+    pub last_modified: Option<DateTime<Local>>,
+}
+
+impl ServiceStatus {
+    /// Collect the current status of a service
+    pub async fn collect(service: &ServiceConfig, global: &GlobalSettings) -> Self {
+        let commit = git_service::current_commit(service, global).await.unwrap_or_else(|e| {
+            warn!(service = service.name; "Failed to read current commit: {}", e);
+            None
+        });
+        
+        let last_modified = std::fs::metadata(&service.local_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(DateTime::<Local>::from);
+        
+        let healthy = match create_service_handler(service, global) {
+            Ok(handler) => handler.health().await,
+            Err(e) => Err(e),
+        };
+        
+        Self {
+            name: service.name.clone(),
+            commit,
+            container: check_service_status(service).await,
+            healthy,
+            last_modified,
+        }
+    }
+    
+    /// Print the status in a human-readable format
+    pub fn display(&self) {
+        println!("== {} ==", self.name);
+        println!("  Commit:        {}", self.commit.as_deref().unwrap_or("not cloned"));
+        match &self.container {
+            Ok(status) => println!("  Status:        {:?}", status),
+            Err(e) => println!("  Status:        unknown ({})", e),
+        }
+        match &self.healthy {
+            Ok(true) => println!("  Health:        healthy"),
+            Ok(false) => println!("  Health:        unhealthy"),
+            Err(e) => println!("  Health:        unknown ({})", e),
+        }
+        match &self.last_modified {
+            Some(time) => println!("  Last Modified: {}", time.format("%Y-%m-%d %H:%M:%S %Z")),
+            None => println!("  Last Modified: unknown"),
+        }
+    }
+}
+
 /// Wait for a service to become ready (container running)
 pub async fn wait_for_service_ready(
     service: &ServiceConfig, 
     max_attempts: u32, 
     delay: Duration
 ) -> Result<bool> {
-    info!("[{}] Waiting for service to become ready", service.name);
+    info!(service = service.name; "Waiting for service to become ready");
     
     for attempt in 1..=max_attempts {
-        debug!("[{}] Checking service readiness (attempt {}/{})", 
-               service.name, attempt, max_attempts);
+        debug!(service = service.name; "Checking service readiness (attempt {}/{})", 
+                                       attempt, max_attempts);
         
         let status = check_service_status(service).await?;
         
         if status == ContainerStatus::Running {
-            info!("[{}] Service is ready and running", service.name);
+            info!(service = service.name; "Service is ready and running");
             return Ok(true);
         }
         
@@ -235,12 +497,115 @@ pub async fn wait_for_service_ready(
         }
     }
     
-    warn!("[{}] Service not ready after {} attempts", service.name, max_attempts);
+    warn!(service = service.name; "Service not ready after {} attempts", max_attempts);
     Ok(false)
 }
 
-/// ServiceHandler trait for working with custom service types
-pub trait ServiceHandler {
+/// Wait for the service to be running again after a restart, as configured by `readiness_attempts`
+pub async fn ensure_service_ready(service: &ServiceConfig) -> Result<()> {
+    if service.readiness_attempts == 0 {
+        return Ok(());
+    }
+    
+    if wait_for_service_ready(service, service.readiness_attempts, service.readiness_delay_duration()).await? {
+        Ok(())
+    } else {
+        Err(anyhow!("Service {} did not become ready after {} checks", service.name, service.readiness_attempts))
+    }
+}
+
+/// Request the service's `health_url` after a restart until it returns a success status,
+/// as configured by `health_check_attempts`
+pub async fn wait_for_health_url(service: &ServiceConfig) -> Result<()> {
+    let url = match &service.health_url {
+        Some(url) if service.health_check_attempts > 0 => url,
+        _ => return Ok(()),
+    };
+    
+    let max_attempts = service.health_check_attempts;
+    let delay = service.health_check_delay_duration();
+    let client = reqwest::Client::new();
+    info!(service = service.name; "Waiting for health URL to return a success status: {}", url);
+    
+    for attempt in 1..=max_attempts {
+        match client.get(url).timeout(HEALTH_URL_TIMEOUT).send().await {
+            Ok(response) if response.status().is_success() => {
+                info!(service = service.name; "Service is healthy");
+                return Ok(());
+            },
+            Ok(response) => debug!(service = service.name; "Health URL returned status {} (attempt {}/{})",
+                                                           response.status(), attempt, max_attempts),
+            Err(e) => debug!(service = service.name; "Health URL request failed (attempt {}/{}): {}",
+                                                     attempt, max_attempts, e),
+        }
+        
+        if attempt < max_attempts {
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    Err(anyhow!("Service {} did not return a success status at {} after {} attempts", 
+                service.name, url, max_attempts))
+}
+
+/// Check that the restarted container is attached to every network in `expected_networks`
+pub async fn check_expected_networks(service: &ServiceConfig) -> Result<()> {
+    if service.expected_networks.is_empty() {
+        return Ok(());
+    }
+    
+    let attached = get_container_networks(&service.container_name).await?;
+    let missing: Vec<&str> = service.expected_networks.iter()
+        .filter(|network| !attached.contains(network))
+        .map(String::as_str)
+        .collect();
+    
+    if !missing.is_empty() {
+        return Err(anyhow!("Container {} is not attached to network(s) {} (attached: {})", 
+                           service.container_name, missing.join(", "), attached.join(", ")));
+    }
+    
+    debug!(service = service.name; "Container is attached to all expected networks");
+    Ok(())
+}
+
+/// Retry the service's readiness command after a restart until it exits 0
+pub async fn wait_for_readiness_command(service: &ServiceConfig, global: &GlobalSettings) -> Result<()> {
+    let cmd = match &service.readiness_command {
+        Some(cmd) => cmd,
+        None => return Ok(()),
+    };
+    
+    let max_attempts = global.retry_attempts.max(1);
+    let delay = global.retry_delay_duration();
+    info!(service = service.name; "Waiting for readiness command to succeed: {}", cmd);
+    
+    for attempt in 1..=max_attempts {
+        debug!(service = service.name; "Running readiness command (attempt {}/{})", attempt, max_attempts);
+        
+        match timeout(Duration::from_secs(DEFAULT_COMMAND_TIMEOUT), shell_command(service, cmd).output()).await {
+            Ok(Ok(output)) if output.status.success() => {
+                info!(service = service.name; "Service is ready");
+                return Ok(());
+            },
+            Ok(Ok(output)) => debug!(service = service.name; "Readiness command exited with {:?}: {}", 
+                                                             output.status.code(), 
+                                     String::from_utf8_lossy(&output.stderr).trim()),
+            Ok(Err(e)) => return Err(anyhow!("Failed to execute readiness command for service {}: {}", service.name, e)),
+            Err(_) => debug!(service = service.name; "Readiness command timed out"),
+        }
+        
+        if attempt < max_attempts {
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    Err(anyhow!("Service {} not ready after {} readiness checks", service.name, max_attempts))
+}
+
+/// ServiceHandler trait for polymorphic service operations
+#[async_trait]
+pub trait ServiceHandler: Send + Sync {
     /// Get the service name
     fn name(&self) -> &str;
     
@@ -259,6 +624,202 @@ pub trait ServiceHandler {
     /// Fix service permissions
     async fn fix_permissions(&self) -> Result<()>;
     
-    /// Monitor service logs and status
-    async fn monitor(&self) -> Result<()>;
-}
\ No newline at end of file
+    /// Monitor service logs and status, returning any problems found
+    async fn monitor(&self) -> Result<Vec<String>>;
+    
+    /// Check whether the service is healthy right now, independently of whether its configuration is valid
+    async fn health(&self) -> Result<bool>;
+}
+
+/// Service handler for types without dedicated support (generic containers, Kubernetes)
+pub struct GenericService<'a> {
+    service: &'a ServiceConfig,
+    global: &'a GlobalSettings,
+}
+
+impl<'a> GenericService<'a> {
+    /// Create a new GenericService instance
+    pub fn new(service: &'a ServiceConfig, global: &'a GlobalSettings) -> Self {
+        Self { service, global }
+    }
+}
+
+#[async_trait]
+impl<'a> ServiceHandler for GenericService<'a> {
+    fn name(&self) -> &str {
+        &self.service.name
+    }
+    
+    fn service_type(&self) -> ServiceType {
+        self.service.service_type.clone()
+    }
+    
+    async fn validate(&self) -> Result<bool> {
+        let cmd = match self.service.effective_validation_command(self.global) {
+            Some(cmd) => cmd,
+            None => return Ok(true),
+        };
+        
+        match run_validation(self.service, &cmd).await {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                warn!(service = self.service.name; "{}", e);
+                Ok(false)
+            }
+        }
+    }
+    
+    async fn restart(&self) -> Result<()> {
+        restart_service(self.service, self.global).await
+    }
+    
+    async fn fix_issues(&self) -> Result<()> {
+        // Generic services have no built-in fixes
+        Ok(())
+    }
+    
+    async fn fix_permissions(&self) -> Result<()> {
+        if !self.service.effective_fix_permissions(self.global.fix_permissions) {
+            return Ok(());
+        }
+        
+        match &self.service.permissions {
+            Some(permissions) => fix_permissions(self.service, permissions, self.global).await,
+            None => Ok(()),
+        }
+    }
+    
+    async fn monitor(&self) -> Result<Vec<String>> {
+        match check_service_status(self.service).await? {
+            ContainerStatus::Running => check_log_files(self.service, self.global).await,
+            status => Ok(vec![format!("Service is not running: {:?}", status)]),
+        }
+    }
+    
+    async fn health(&self) -> Result<bool> {
+        check_health(self.service, self.global).await
+    }
+}
+
+/// Check whether a service is healthy right now
+///
+/// Runs the health command if there is one, else requests the health URL, else falls back to
+/// the container (or deployment) running and passing its own healthcheck, if it defines one.
+pub async fn check_health(service: &ServiceConfig, global: &GlobalSettings) -> Result<bool> {
+    if let Some(cmd) = service.effective_health_command(global) {
+        debug!(service = service.name; "Running health command: {}", cmd);
+        let output = timeout(Duration::from_secs(DEFAULT_COMMAND_TIMEOUT), shell_command(service, &cmd).output()).await
+            .context(format!("Health command timed out for service {}", service.name))?
+            .context(format!("Failed to execute health command for service {}", service.name))?;
+        return Ok(output.status.success());
+    }
+    
+    if let Some(url) = &service.health_url {
+        debug!(service = service.name; "Requesting health URL: {}", url);
+        // An unreachable service is unhealthy, not an error in the check
+        return Ok(match reqwest::Client::new().get(url).timeout(HEALTH_URL_TIMEOUT).send().await {
+            Ok(response) => response.status().is_success(),
+            Err(e) => {
+                debug!(service = service.name; "Health URL request failed: {}", e);
+                false
+            }
+        });
+    }
+    
+    match check_service_status(service).await? {
+        ContainerStatus::Running if service.service_type == ServiceType::Kubernetes => Ok(true),
+        ContainerStatus::Running => Ok(get_container_health(&service.container_name).await?
+            .is_none_or(|health| health == "healthy")),
+        _ => Ok(false),
+    }
+}
+
+/// Tail the service's `log_files` inside its container and report the lines matching their error patterns
+pub async fn check_log_files(service: &ServiceConfig, global: &GlobalSettings) -> Result<Vec<String>> {
+    if service.log_files.is_empty() || check_container_status(&service.container_name).await? != ContainerStatus::Running {
+        return Ok(vec![]);
+    }
+    
+    let examples = service.effective_log_error_examples(global);
+    let mut problems = Vec::new();
+    
+    for log_file in &service.log_files {
+        let patterns = log_file.error_regexes()?;
+        let logs = match tail_container_file(&service.container_name, &log_file.path, service.log_tail_lines).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                problems.push(format!("{}", e));
+                continue;
+            }
+        };
+        
+        let errors: Vec<&str> = logs.lines()
+            .filter(|line| patterns.iter().any(|pattern| pattern.is_match(line)))
+            .collect();
+        if errors.is_empty() {
+            continue;
+        }
+        
+        // Report the most recent errors
+        problems.push(format!("Found {} errors in {}", errors.len(), log_file.path));
+        problems.extend(errors.iter().rev().take(examples).map(|line| format!("{}: {}", log_file.path, line)));
+    }
+    
+    Ok(problems)
+}
+
+/// Create a service handler based on service type
+pub fn create_service_handler<'a>(
+    service: &'a ServiceConfig, 
+    global: &'a GlobalSettings
+) -> Result<Box<dyn ServiceHandler + 'a>> {
+    match service.service_type {
+        ServiceType::Nginx => {
+            let nginx = NginxService::new(service, global)?;
+            Ok(Box::new(nginx))
+        },
+        ServiceType::Apache => {
+            let apache = ApacheService::new(service, global)?;
+            Ok(Box::new(apache))
+        },
+        ServiceType::Custom(_) => {
+            let custom = CustomCommandService::new(service, global)?;
+            Ok(Box::new(custom))
+        },
+        _ => Ok(Box::new(GenericService::new(service, global))),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    #[tokio::test]
+    async fn test_wait_for_health_url() {
+        // Fails the first request, then reports healthy
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/health", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = stream.read(&mut [0; 1024]).await;
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        
+        let mut service = ServiceConfig::default_nginx();
+        service.health_url = Some(url);
+        service.health_check_attempts = 3;
+        service.health_check_delay = "0s".to_string();
+        assert!(wait_for_health_url(&service).await.is_ok());
+        
+        // The server is gone, so no attempt succeeds
+        service.health_check_attempts = 2;
+        assert!(wait_for_health_url(&service).await.is_err());
+        
+        service.health_url = None;
+        assert!(wait_for_health_url(&service).await.is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+use log::info;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::time::{Duration, Instant};
+
+/// Counters of every service monitored since startup, kept across config reloads
+static COUNTERS: LazyLock<Mutex<BTreeMap<String, Arc<ServiceCounters>>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// What the monitoring task of a service has done since the watcher started
+#[derive(Debug)]
+pub struct ServiceCounters {
+    started: Instant,
+    checks: AtomicU64,
+    updates: AtomicU64,
+    restarts: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl ServiceCounters {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            checks: AtomicU64::new(0),
+            updates: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a completed update check, successful or not
+    pub fn record_check(&self) {
+        self.checks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count an update that was applied
+    pub fn record_update(&self) {
+        self.updates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a restart of the service
+    pub fn record_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a failed check
+    pub fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Time since the service was first monitored
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Get the counters of a service, creating them the first time it is monitored
+pub fn counters(name: &str) -> Arc<ServiceCounters> {
+    match COUNTERS.lock() {
+        Ok(mut counters) => counters.entry(name.to_string())
+            .or_insert_with(|| Arc::new(ServiceCounters::new()))
+            .clone(),
+        // Counting is best effort; a poisoned registry only loses the summary
+        Err(_) => Arc::new(ServiceCounters::new()),
+    }
+}
+
+/// Format a duration as e.g. `2h05m09s`
+fn format_uptime(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
+
+/// Table of the counters of every service, one row per service
+pub fn summary() -> Vec<String> {
+    let counters = match COUNTERS.lock() {
+        Ok(counters) => counters.clone(),
+        Err(_) => return vec![],
+    };
+    
+    let width = counters.keys().map(String::len).chain(["SERVICE".len()]).max().unwrap_or(0);
+    let mut lines = vec![format!("{:<width$}  {:>7}  {:>7}  {:>8}  {:>8}  {:>10}",
+                                 "SERVICE", "CHECKS", "UPDATES", "RESTARTS", "FAILURES", "UPTIME")];
+    
+    for (name, counter) in &counters {
+        lines.push(format!("{:<width$}  {:>7}  {:>7}  {:>8}  {:>8}  {:>10}",
+                           name,
+                           counter.checks.load(Ordering::Relaxed),
+                           counter.updates.load(Ordering::Relaxed),
+                           counter.restarts.load(Ordering::Relaxed),
+                           counter.failures.load(Ordering::Relaxed),
+                           format_uptime(counter.uptime())));
+    }
+    
+    lines
+}
+
+/// Log the summary table, e.g. at shutdown
+pub fn log_summary() {
+    let lines = summary();
+    if lines.len() <= 1 {
+        return;
+    }
+    
+    info!("Summary of this run:");
+    for line in lines {
+        info!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_summary_counts_per_service() {
+        let web = counters("stats-test-web");
+        web.record_check();
+        web.record_check();
+        web.record_update();
+        web.record_restart();
+        counters("stats-test-web").record_failure();
+        
+        let row = summary().into_iter()
+            .find(|line| line.starts_with("stats-test-web "))
+            .unwrap();
+        let columns: Vec<&str> = row.split_whitespace().collect();
+        assert_eq!(&columns[..5], ["stats-test-web", "2", "1", "1", "1"]);
+        
+        assert_eq!(format_uptime(Duration::from_secs(7509)), "2h05m09s");
+        assert_eq!(format_uptime(Duration::from_secs(42)), "42s");
+    }
+}
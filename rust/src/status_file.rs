@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Last known state of every monitored service, as written to the status file
+static STATES: LazyLock<Mutex<BTreeMap<String, ServiceState>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Status files whose last write failed, and whether writing them is still retried
+static FAILED_PATHS: LazyLock<Mutex<HashMap<PathBuf, bool>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How the last monitor cycle of a service ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleOutcome {
+    /// The remote had nothing new to apply
+    NoChanges,
+    /// An update was pulled and applied
+    Updated,
+    /// The check or the update failed
+    Failed,
+    /// Validation failures opened the circuit breaker
+    CircuitOpen,
+    /// The cycle was aborted after `cycle_timeout`
+    TimedOut,
+}
+
+/// State of a service after its last monitor cycle
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceState {
+    /// RFC 3339 time the last cycle finished
+    pub last_check: String,
+    pub commit: Option<String>,
+    pub container_status: Option<String>,
+    /// Whether the service's handler reported it healthy, None if the check failed
+    pub healthy: Option<bool>,
+    pub last_outcome: CycleOutcome,
+    /// Consecutive failed cycles, 0 after a success
+    pub failure_streak: u32,
+}
+
+/// Contents of the status file
+#[derive(Serialize)]
+struct StatusFile<'a> {
+    /// RFC 3339 time the file was written
+    updated_at: String,
+    services: &'a BTreeMap<String, ServiceState>,
+}
+
+/// Record the state of a service and rewrite the status file at `path`
+pub fn record(name: &str, state: ServiceState, path: &Path) -> Result<()> {
+    let mut states = STATES.lock()
+        .map_err(|_| anyhow!("Status registry is poisoned"))?;
+    states.insert(name.to_string(), state);
+    
+    // Written while holding the lock, so concurrent cycles cannot interleave their renames
+    let status = StatusFile {
+        updated_at: Utc::now().to_rfc3339(),
+        services: &states,
+    };
+    let content = serde_json::to_string_pretty(&status)
+        .context("Failed to serialize status")?;
+    write_atomic(path, content.as_bytes())?;
+    
+    if let Ok(mut failed) = FAILED_PATHS.lock() {
+        failed.remove(path);
+    }
+    Ok(())
+}
+
+/// Whether the status file at `path` is still written; a permission error stops it until restart
+pub fn writable(path: &Path) -> bool {
+    FAILED_PATHS.lock()
+        .map(|failed| failed.get(path) != Some(&false))
+        .unwrap_or(true)
+}
+
+/// Note that writing `path` failed, returning whether this is the first failure since it last worked
+pub fn record_failure(path: &Path, error: &anyhow::Error) -> bool {
+    // The directory will not become writable on its own, so don't pay for every cycle's status
+    let retry = !error.chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| matches!(e.kind(), io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem));
+    
+    match FAILED_PATHS.lock() {
+        Ok(mut failed) => failed.insert(path.to_path_buf(), retry).is_none(),
+        Err(_) => true,
+    }
+}
+
+/// Drop a service that is no longer monitored; it disappears from the file on the next write
+pub fn forget(name: &str) {
+    if let Ok(mut states) = STATES.lock() {
+        states.remove(name);
+    }
+}
+
+/// Temporary file `path` is written through, next to it so the rename stays on one filesystem
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", name))
+}
+
+/// Replace `path` with `content` so readers see either the old or the new file, never a partial one
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let temp = temp_path(path);
+    fs::write(&temp, content)
+        .context(format!("Failed to write {}", temp.display()))?;
+    
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    }).context(format!("Failed to replace {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_record_writes_status_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("watcher-status.json");
+        
+        for streak in [1, 2] {
+            let state = ServiceState {
+                last_check: Utc::now().to_rfc3339(),
+                commit: Some("abc123".to_string()),
+                container_status: None,
+                healthy: None,
+                last_outcome: CycleOutcome::Failed,
+                failure_streak: streak,
+            };
+            record("status-test-web", state, &path).unwrap();
+        }
+        
+        let status: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let service = &status["services"]["status-test-web"];
+        assert_eq!(service["last_outcome"], "failed");
+        assert_eq!(service["failure_streak"], 2);
+        assert!(!temp_path(&path).exists());
+    }
+    
+    #[test]
+    fn test_permission_error_stops_writes() {
+        let path = Path::new("/status-test/watcher-status.json");
+        let error = anyhow::Error::from(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context("Failed to write status file");
+        
+        assert!(writable(path));
+        assert!(record_failure(path, &error));
+        assert!(!record_failure(path, &error));
+        assert!(!writable(path));
+        
+        // Other failures are warned about once but still retried
+        let other = Path::new("/status-test/other.json");
+        assert!(record_failure(other, &anyhow!("disk full")));
+        assert!(!record_failure(other, &anyhow!("disk full")));
+        assert!(writable(other));
+    }
+}
@@ -1,13 +1,15 @@
 use anyhow::{anyhow, Context, Result};
 use log::{debug, info, warn};
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tokio::process::Command;
 use tokio::time::Duration;
-use url::Url;
 
-use crate::config::{Permissions, ServiceConfig};
+use crate::config::{Config, GlobalSettings, Permissions, ServiceConfig, ServiceType};
+use crate::docker_utils::{check_container_status, container_runtime, detect_docker_compose_command, ContainerStatus};
 
 //--------------------------------
 // Process Management Functions
@@ -95,8 +97,26 @@ pub async fn check_pid_file(path: impl AsRef<Path>) -> Result<Option<u32>> {
 pub async fn is_process_running(pid: u32) -> Result<bool> {
     #[cfg(unix)]
     {
-        let proc_path = format!("/proc/{}", pid);
-        return Ok(Path::new(&proc_path).exists());
+        // Checking /proc is cheap on Linux, but it may not be mounted
+        #[cfg(target_os = "linux")]
+        if Path::new(&format!("/proc/{}", pid)).exists() {
+            return Ok(true);
+        }
+        
+        // PID 0 and values beyond pid_t would address process groups
+        let pid = match libc::pid_t::try_from(pid) {
+            Ok(pid) if pid > 0 => pid,
+            _ => return Ok(false),
+        };
+        
+        // Signal 0 only checks whether the process can be signalled
+        // SAFETY: kill with signal 0 does not affect the target process
+        if unsafe { libc::kill(pid, 0) } == 0 {
+            return Ok(true);
+        }
+        
+        // EPERM means the process exists but belongs to another user
+        return Ok(std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM));
     }
     
     #[cfg(windows)]
@@ -127,64 +147,235 @@ pub async fn is_process_running(pid: u32) -> Result<bool> {
 // Dependency Checking
 //--------------------------------
 
-/// Check if required system dependencies are available
-pub async fn check_dependencies() -> Result<()> {
+/// Check that the binaries needed by the configured services are installed,
+/// reporting every missing one at once
+pub async fn check_dependencies(config: &Config) -> Result<()> {
     info!("Checking for required dependencies");
     
-    // Essential dependencies
-    let dependencies = ["git", "docker", "chown", "chmod", "find"];
+    // Binary -> services that need it
+    let mut required: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut uses_compose = false;
     
-    for dep in dependencies {
-        let status = Command::new("which")
-            .arg(dep)
-            .status()
-            .await
-            .context(format!("Failed to check for dependency: {}", dep))?;
+    for service in &config.services {
+        let global = &config.global_settings;
+        let mut needs = vec!["git", "sh"];
+        
+        match service.service_type {
+            ServiceType::Kubernetes => needs.push("kubectl"),
+            ServiceType::Custom(_) => {}
+            // Generic services with their own restart command (e.g. systemd units) don't need docker
+            ServiceType::Generic if service.effective_restart_command(global).is_some()
+                && !service.validation_in_container => {}
+            _ => needs.push(global.container_runtime.binary()),
+        }
         
-        if !status.success() {
-            return Err(anyhow!("Required dependency not found: {}", dep));
+        if service.use_docker_compose || global.use_docker_compose {
+            uses_compose = true;
         }
+        
+        if service.effective_fix_permissions(global.fix_permissions) && service.permissions.is_some() {
+            needs.extend(["chown", "chmod", "find"]);
+        }
+        
+        for binary in needs {
+            required.entry(binary).or_default().push(&service.name);
+        }
+    }
+    
+    let missing: Vec<String> = required.iter()
+        .filter(|(binary, _)| find_in_path(binary).is_none())
+        .map(|(binary, services)| format!("{} (needed by {})", binary, services.join(", ")))
+        .collect();
+    
+    if !missing.is_empty() {
+        return Err(anyhow!("Required binaries not found in PATH: {}", missing.join("; ")));
     }
     
     // Check for Docker Compose
-    let docker_compose_v2 = Command::new("docker")
-        .args(["compose", "version"])
-        .output()
-        .await;
-    
-    let docker_compose_legacy = Command::new("docker-compose")
-        .arg("--version")
-        .output()
-        .await;
-    
-    if docker_compose_v2.is_err() && docker_compose_legacy.is_err() {
-        warn!("Neither 'docker compose' nor 'docker-compose' are available in PATH");
-    } else {
-        debug!("Docker Compose is available");
+    if uses_compose {
+        let (compose_cmd, is_v2) = detect_docker_compose_command().await;
+        
+        if !is_v2 && find_in_path(&compose_cmd).is_none() {
+            let runtime = config.global_settings.container_runtime;
+            warn!("Neither '{} compose' nor '{}' are available in PATH", runtime.binary(), runtime.compose_binary());
+        } else {
+            debug!("Compose is available ({})", compose_cmd);
+        }
     }
     
     info!("All required dependencies are available");
     Ok(())
 }
 
+/// Warn at startup about services whose container does not exist, so a typo in
+/// `container_name` is caught before the first deploy rather than at restart time
+pub async fn check_containers_exist(config: &Config) {
+    let global = &config.global_settings;
+    let mut missing = Vec::new();
+    
+    for service in &config.services {
+        // Compose can create the container itself; other service types don't restart a container
+        let uses_container = match service.service_type {
+            ServiceType::Kubernetes | ServiceType::Custom(_) => false,
+            ServiceType::Generic => service.effective_restart_command(global).is_none()
+                || service.validation_in_container,
+            _ => true,
+        };
+        
+        if !uses_container || service.use_docker_compose || global.use_docker_compose {
+            continue;
+        }
+        
+        match check_container_status(&service.container_name).await {
+            Ok(ContainerStatus::NotExists) => {
+                missing.push(format!("{} (service {})", service.container_name, service.name));
+            }
+            Ok(_) => {}
+            Err(e) => missing.push(format!("{} (service {}: {})", service.container_name, service.name, e)),
+        }
+    }
+    
+    if !missing.is_empty() {
+        warn!("Containers not found, check container_name in the configuration: {}", missing.join(", "));
+    }
+}
+
+/// Print the versions of the external tools the watcher uses, for diagnosing installations
+pub async fn print_dependency_report() {
+    let report = |name: &str, version: Option<String>| {
+        println!("{:<16} {}", format!("{}:", name), version.unwrap_or_else(|| "not found".to_string()));
+    };
+    
+    report("git", tool_version("git", &["--version"]).await);
+    let runtime = container_runtime().binary();
+    report(runtime, tool_version(runtime, &["--version"]).await);
+    
+    let (compose_cmd, is_v2) = detect_docker_compose_command().await;
+    let compose_version = if is_v2 {
+        tool_version(runtime, &["compose", "version", "--short"]).await
+    } else {
+        tool_version(&compose_cmd, &["version", "--short"]).await
+    };
+    report(&format!("{} compose", runtime), compose_version.map(|version| {
+        format!("{} ({}, {})", version, compose_cmd, if is_v2 { "v2" } else { "v1" })
+    }));
+    
+    // ssh-add exits with 2 when it cannot reach an agent, and 1 when the agent holds no keys
+    let ssh_agent = match std::env::var("SSH_AUTH_SOCK") {
+        Ok(socket) => match Command::new("ssh-add").arg("-l").output().await {
+            Ok(output) if output.status.code() == Some(2) => format!("not reachable at {}", socket),
+            Ok(output) if output.status.success() => {
+                let keys = String::from_utf8_lossy(&output.stdout).lines().count();
+                format!("available at {} ({} keys)", socket, keys)
+            }
+            Ok(_) => format!("available at {} (no keys)", socket),
+            Err(_) => format!("SSH_AUTH_SOCK set to {}, but ssh-add is not installed", socket),
+        },
+        Err(_) => "not configured (SSH_AUTH_SOCK is not set)".to_string(),
+    };
+    report("ssh agent", Some(ssh_agent));
+}
+
+/// Get the first line a tool prints for its version, or None if it cannot be run
+async fn tool_version(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().await.ok()?;
+    
+    if !output.status.success() {
+        return None;
+    }
+    
+    String::from_utf8_lossy(&output.stdout).lines().next().map(|line| line.trim().to_string())
+}
+
+/// Find an executable in PATH
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Check whether a path is an executable file
+fn is_executable(path: &Path) -> bool {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return false,
+    };
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
 //--------------------------------
 // Permission Management
 //--------------------------------
 
+/// Directories that are never changed recursively, whatever their depth
+const PROTECTED_PERMISSION_PATHS: &[&str] = &["/", "/etc", "/usr"];
+
+/// Refuse to recursively change ownership of system directories or paths too close to the root
+pub fn ensure_safe_permission_path(path: &Path, global: &GlobalSettings) -> Result<()> {
+    if global.allow_unsafe_permission_paths {
+        return Ok(());
+    }
+    
+    // Resolve symlinks and ".." so they cannot hide the real target
+    let resolved = fs::canonicalize(path).unwrap_or_else(|_| {
+        path.components().fold(PathBuf::new(), |mut resolved, component| {
+            match component {
+                std::path::Component::ParentDir => { resolved.pop(); }
+                std::path::Component::CurDir => {}
+                other => resolved.push(other),
+            }
+            resolved
+        })
+    });
+    
+    let home = dirs::home_dir();
+    if PROTECTED_PERMISSION_PATHS.iter().any(|protected| resolved == Path::new(protected)) ||
+       home.as_deref() == Some(resolved.as_path()) {
+        return Err(anyhow!("Refusing to change permissions of protected directory {} \
+                            (set allow_unsafe_permission_paths to override)", resolved.display()));
+    }
+    
+    let depth = resolved.components()
+        .filter(|component| matches!(component, std::path::Component::Normal(_)))
+        .count();
+    if depth < global.min_permission_path_depth {
+        return Err(anyhow!("Refusing to change permissions of {}: it is {} levels deep, below the minimum of {} \
+                            (set allow_unsafe_permission_paths to override)", 
+                           resolved.display(), depth, global.min_permission_path_depth));
+    }
+    
+    Ok(())
+}
+
 /// Fix permissions for a service's files
-pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions) -> Result<()> {
+pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions, global: &GlobalSettings) -> Result<()> {
     let path = &service.local_path;
     let user = &permissions.user;
     let group = &permissions.group;
     
-    debug!("[{}] Fixing permissions for {} to {}:{}", 
-           service.name, path.display(), user, group);
+    debug!(service = service.name; "Fixing permissions for {} to {}:{}", 
+                                   path.display(), user, group);
     
     // Make sure directory exists first
     if !path.exists() {
         return Err(anyhow!("Directory does not exist: {}", path.display()));
     }
     
+    ensure_safe_permission_path(path, global)?;
+    
     // Fix ownership
     let chown_status = Command::new("chown")
         .args(["-R", &format!("{}:{}", user, group), &path.to_string_lossy()])
@@ -195,8 +386,8 @@ pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions)
     if !chown_status.success() {
         // Try with numeric IDs if available
         if let (Ok(uid), Ok(gid)) = (std::env::var("USER_ID"), std::env::var("GROUP_ID")) {
-            warn!("[{}] Failed to fix permissions with named user/group, trying with numeric IDs: {}:{}", 
-                  service.name, uid, gid);
+            warn!(service = service.name; "Failed to fix permissions with named user/group, trying with numeric IDs: {}:{}", 
+                                          uid, gid);
             
             let numeric_owner = format!("{}:{}", uid, gid);
             let status = Command::new("chown")
@@ -225,7 +416,7 @@ pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions)
         .context(format!("Failed to execute chmod for directories in {}", service.name))?;
     
     if !dir_chmod_status.success() {
-        warn!("[{}] Failed to set directory permissions to 750", service.name);
+        warn!(service = service.name; "Failed to set directory permissions to 750");
     }
     
     // Fix file permissions
@@ -240,7 +431,7 @@ pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions)
         .context(format!("Failed to execute chmod for files in {}", service.name))?;
     
     if !file_chmod_status.success() {
-        warn!("[{}] Failed to set file permissions to 640", service.name);
+        warn!(service = service.name; "Failed to set file permissions to 640");
     }
     
     // Fix execution permissions for scripts
@@ -256,14 +447,115 @@ pub async fn fix_permissions(service: &ServiceConfig, permissions: &Permissions)
         .context(format!("Failed to execute chmod for script files in {}", service.name))?;
     
     if !script_chmod_status.success() {
-        warn!("[{}] Failed to set script permissions to 750", service.name);
+        warn!(service = service.name; "Failed to set script permissions to 750");
     }
     
-    info!("[{}] Fixed permissions for {} to {}:{}", 
-          service.name, path.display(), user, group);
+    info!(service = service.name; "Fixed permissions for {} to {}:{}", 
+                                  path.display(), user, group);
     Ok(())
 }
 
+//--------------------------------
+// Privilege Dropping
+//--------------------------------
+
+/// User and group that git, validation and custom commands run as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunAs {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Identity set from `run_as_user`/`run_as_group`; None runs commands as the watcher itself
+static RUN_AS: Mutex<Option<RunAs>> = Mutex::new(None);
+
+/// Resolve `run_as_user`/`run_as_group` (names or numeric ids) and apply them to unprivileged commands
+pub fn set_run_as(global: &GlobalSettings) -> Result<()> {
+    let run_as = resolve_run_as(global.run_as_user.as_deref(), global.run_as_group.as_deref())?;
+    if let Some(run_as) = run_as {
+        info!("Running git, validation and custom commands as uid {} gid {}", run_as.uid, run_as.gid);
+    }
+    
+    if let Ok(mut current) = RUN_AS.lock() {
+        *current = run_as;
+    }
+    Ok(())
+}
+
+/// Identity unprivileged commands currently run as, if it was changed
+pub fn current_run_as() -> Option<RunAs> {
+    RUN_AS.lock().ok().and_then(|current| *current)
+}
+
+/// Look up the uid and gid to run as; the user's primary group is used if no group is given
+#[cfg(unix)]
+fn resolve_run_as(user: Option<&str>, group: Option<&str>) -> Result<Option<RunAs>> {
+    use std::ffi::CString;
+    
+    if user.is_none() && group.is_none() {
+        return Ok(None);
+    }
+    
+    // SAFETY: geteuid/getegid have no preconditions
+    let (mut uid, mut gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    
+    if let Some(user) = user {
+        match user.parse::<u32>() {
+            Ok(id) => uid = id,
+            Err(_) => {
+                let name = CString::new(user).context("Invalid run_as_user")?;
+                // SAFETY: getpwnam returns null or a pointer to a static passwd entry, read immediately
+                let entry = unsafe { libc::getpwnam(name.as_ptr()) };
+                if entry.is_null() {
+                    return Err(anyhow!("Unknown run_as_user: {}", user));
+                }
+                unsafe {
+                    uid = (*entry).pw_uid;
+                    gid = (*entry).pw_gid;
+                }
+            }
+        }
+    }
+    
+    if let Some(group) = group {
+        match group.parse::<u32>() {
+            Ok(id) => gid = id,
+            Err(_) => {
+                let name = CString::new(group).context("Invalid run_as_group")?;
+                // SAFETY: getgrnam returns null or a pointer to a static group entry, read immediately
+                let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+                if entry.is_null() {
+                    return Err(anyhow!("Unknown run_as_group: {}", group));
+                }
+                gid = unsafe { (*entry).gr_gid };
+            }
+        }
+    }
+    
+    Ok(Some(RunAs { uid, gid }))
+}
+
+#[cfg(not(unix))]
+fn resolve_run_as(user: Option<&str>, group: Option<&str>) -> Result<Option<RunAs>> {
+    if user.is_some() || group.is_some() {
+        return Err(anyhow!("run_as_user and run_as_group are only supported on unix"));
+    }
+    Ok(None)
+}
+
+/// Create a command that runs with the configured `run_as` identity instead of the watcher's
+pub fn unprivileged_command(program: &str) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(program);
+    
+    #[cfg(unix)]
+    if let Some(run_as) = current_run_as() {
+        cmd.uid(run_as.uid).gid(run_as.gid);
+    }
+    
+    cmd
+}
+
 //--------------------------------
 // SSH Key Management
 //--------------------------------
@@ -373,44 +665,6 @@ pub async fn setup_ssh_auth(key_content: &str) -> Result<PathBuf> {
     Ok(key_path)
 }
 
-//--------------------------------
-// Health Check Notifications
-//--------------------------------
-
-/// Notify a health check service
-pub async fn notify_healthcheck(url: &str, message: &str, is_error: bool) -> Result<()> {
-    // Validate URL
-    let parsed_url = Url::parse(url)
-        .context(format!("Invalid health check URL: {}", url))?;
-    
-    debug!("Notifying health check service: {}", parsed_url);
-    
-    // Build the full URL with message
-    let full_url = if is_error {
-        format!("{}?status=fail&msg={}", url, urlencoding::encode(message))
-    } else {
-        format!("{}?msg={}", url, urlencoding::encode(message))
-    };
-    
-    // Send the request
-    let client = reqwest::Client::new();
-    let response = client.get(&full_url)
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-        .context("Failed to send health check notification")?;
-    
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "Health check notification failed with status: {}", 
-            response.status()
-        ));
-    }
-    
-    info!("Health check notification sent successfully");
-    Ok(())
-}
-
 //--------------------------------
 // Duration and Time Functions
 //--------------------------------
@@ -500,6 +754,28 @@ mod tests {
         assert!(parse_duration("30x").is_err());
     }
     
+    #[test]
+    fn test_ensure_safe_permission_path() {
+        let global = GlobalSettings::default();
+        
+        assert!(ensure_safe_permission_path(Path::new("/"), &global).is_err());
+        assert!(ensure_safe_permission_path(Path::new("/etc"), &global).is_err());
+        assert!(ensure_safe_permission_path(Path::new("/app"), &global).is_err());
+        assert!(ensure_safe_permission_path(Path::new("/app/config/../.."), &global).is_err());
+        assert!(ensure_safe_permission_path(Path::new("/app/config"), &global).is_ok());
+        
+        let global = GlobalSettings { allow_unsafe_permission_paths: true, ..GlobalSettings::default() };
+        assert!(ensure_safe_permission_path(Path::new("/"), &global).is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_is_process_running() -> Result<()> {
+        assert!(is_process_running(std::process::id()).await?);
+        assert!(!is_process_running(0).await?);
+        
+        Ok(())
+    }
+    
     #[tokio::test]
     async fn test_pid_file() -> Result<()> {
         let temp_dir = tempdir()?;
@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Context, Result};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+
+use crate::config::{GlobalSettings, ServiceConfig, WebhookProvider};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Repositories and branches a monitoring task watches, with the handle used to wake it
+struct ServiceTrigger {
+    /// Normalized repository URL and branch of the service and each of its extra repositories
+    repos: Vec<(String, String)>,
+    notify: Arc<Notify>,
+}
+
+/// Triggers for all running monitoring tasks, keyed by service name
+static TRIGGERS: LazyLock<Mutex<HashMap<String, ServiceTrigger>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Shared state of the webhook server
+struct WebhookState {
+    secret: Option<String>,
+    /// Provider requests must come from; detected per request from its headers when unset
+    provider: Option<WebhookProvider>,
+}
+
+/// What a webhook request asks for, once its provider-specific payload has been parsed
+#[derive(Debug, PartialEq)]
+enum WebhookEvent {
+    Ping,
+    Push {
+        repo_urls: Vec<String>,
+        branches: Vec<String>,
+    },
+    /// A known event that does not change any branch (tag push, merge request, ...)
+    Ignored(String),
+}
+
+/// Register a service so push events for its repository wake its monitoring task
+pub fn register_service(service: &ServiceConfig, global: &GlobalSettings) -> Arc<Notify> {
+    let notify = Arc::new(Notify::new());
+    let trigger = ServiceTrigger {
+        repos: std::iter::once((
+            normalize_repo_url(&service.repo_url),
+            service.effective_branch(&global.default_branch),
+        ))
+        .chain(service.extra_repos.iter().map(|extra| (
+            normalize_repo_url(&extra.repo_url),
+            extra.effective_branch(service, &global.default_branch),
+        )))
+        .collect(),
+        notify: notify.clone(),
+    };
+    
+    if let Ok(mut triggers) = TRIGGERS.lock() {
+        triggers.insert(service.name.clone(), trigger);
+    }
+    
+    notify
+}
+
+/// Remove a service that is no longer monitored
+pub fn unregister_service(name: &str) {
+    if let Ok(mut triggers) = TRIGGERS.lock() {
+        triggers.remove(name);
+    }
+}
+
+/// Wake a service's monitoring task so it checks immediately, returning false if it is not running
+pub fn trigger_service(name: &str) -> bool {
+    match TRIGGERS.lock() {
+        Ok(triggers) => triggers.get(name)
+            .map(|trigger| trigger.notify.notify_one())
+            .is_some(),
+        Err(_) => false,
+    }
+}
+
+/// Run the webhook server until it fails
+pub async fn serve(port: u16, secret: Option<String>, provider: Option<WebhookProvider>) -> Result<()> {
+    let state = Arc::new(WebhookState { secret, provider });
+    let app = Router::new()
+        .route("/webhook", post(handle_webhook))
+        .route("/services/{name}/check", post(handle_check))
+        .with_state(state);
+    
+    let listener = TcpListener::bind(("0.0.0.0", port)).await
+        .context(format!("Failed to bind webhook server to port {}", port))?;
+    
+    info!("Webhook server listening on port {}", port);
+    axum::serve(listener, app).await
+        .context("Webhook server failed")?;
+    
+    Ok(())
+}
+
+/// Handle a push event from GitHub, GitLab, Bitbucket or Gitea
+async fn handle_webhook(
+    State(state): State<Arc<WebhookState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let provider = match state.provider.or_else(|| detect_provider(&headers)) {
+        Some(provider) => provider,
+        None => {
+            warn!("Rejected webhook request from an unknown provider");
+            return (StatusCode::BAD_REQUEST, "Unknown webhook provider".to_string());
+        }
+    };
+    
+    if let Some(secret) = &state.secret {
+        let credential = match header_value(&headers, signature_header(provider)) {
+            Some(credential) => credential,
+            None => {
+                warn!("Rejected unsigned {:?} webhook request", provider);
+                return (StatusCode::BAD_REQUEST, "Request is not signed".to_string());
+            }
+        };
+        if let Err(e) = verify_request(provider, &body, secret, credential) {
+            warn!("Rejected {:?} webhook request: {}", provider, e);
+            return (StatusCode::UNAUTHORIZED, "Invalid signature".to_string());
+        }
+    }
+    
+    let payload: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Rejected webhook request with invalid payload: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid JSON payload".to_string());
+        }
+    };
+    
+    let (repo_urls, branches) = match parse_event(provider, &headers, &payload) {
+        Ok(WebhookEvent::Ping) => return (StatusCode::OK, "pong".to_string()),
+        Ok(WebhookEvent::Ignored(event)) => {
+            debug!("Ignoring {:?} webhook event {}", provider, event);
+            return (StatusCode::OK, format!("Ignored event: {}", event));
+        }
+        Ok(WebhookEvent::Push { repo_urls, branches }) => (repo_urls, branches),
+        Err(e) => {
+            warn!("Rejected {:?} webhook request: {}", provider, e);
+            return (StatusCode::BAD_REQUEST, e.to_string());
+        }
+    };
+    
+    let triggered = trigger_matching_services(&repo_urls, &branches);
+    if triggered.is_empty() {
+        debug!("Webhook payload did not match any monitored service");
+        return (StatusCode::OK, "No matching services".to_string());
+    }
+    
+    info!("Webhook triggered immediate check for: {}", triggered.join(", "));
+    (StatusCode::ACCEPTED, format!("Triggered: {}", triggered.join(", ")))
+}
+
+/// Trigger an immediate check of a single service
+///
+/// The check runs in the service's own monitoring task, so it never overlaps a scheduled check;
+/// a request arriving mid-check queues another check right after it.
+async fn handle_check(
+    State(state): State<Arc<WebhookState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, String) {
+    if let Some(secret) = &state.secret {
+        let token = header_value(&headers, "authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if token.is_none_or(|token| verify_token(secret, token).is_err()) {
+            warn!("Rejected check request for {}: missing or invalid bearer token", name);
+            return (StatusCode::UNAUTHORIZED, "Invalid token".to_string());
+        }
+    }
+    
+    if !trigger_service(&name) {
+        return (StatusCode::NOT_FOUND, format!("Service {} is not being monitored", name));
+    }
+    
+    info!("Control API triggered immediate check for: {}", name);
+    (StatusCode::ACCEPTED, format!("Triggered: {}", name))
+}
+
+/// Work out which provider sent a request from its event headers
+fn detect_provider(headers: &HeaderMap) -> Option<WebhookProvider> {
+    // Gitea also sends GitHub's headers, so it is checked first
+    if headers.contains_key("x-gitea-event") {
+        Some(WebhookProvider::Gitea)
+    } else if headers.contains_key("x-gitlab-event") {
+        Some(WebhookProvider::Gitlab)
+    } else if headers.contains_key("x-github-event") {
+        Some(WebhookProvider::Github)
+    } else if headers.contains_key("x-event-key") {
+        Some(WebhookProvider::Bitbucket)
+    } else {
+        None
+    }
+}
+
+/// Header carrying the signature or token a provider authenticates requests with
+fn signature_header(provider: WebhookProvider) -> &'static str {
+    match provider {
+        WebhookProvider::Github => "x-hub-signature-256",
+        WebhookProvider::Gitlab => "x-gitlab-token",
+        WebhookProvider::Bitbucket => "x-hub-signature",
+        WebhookProvider::Gitea => "x-gitea-signature",
+    }
+}
+
+/// Verify a request's signature or token against the shared secret
+fn verify_request(provider: WebhookProvider, body: &[u8], secret: &str, credential: &str) -> Result<()> {
+    match provider {
+        WebhookProvider::Github | WebhookProvider::Bitbucket => verify_signature(body, secret, credential),
+        // Gitea sends the bare hex digest
+        WebhookProvider::Gitea => verify_signature(body, secret, &format!("sha256={}", credential)),
+        WebhookProvider::Gitlab => verify_token(secret, credential)
+            .map_err(|_| anyhow!("GitLab token does not match")),
+    }
+}
+
+/// Compare a token with the shared secret in constant time
+///
+/// Both are run through an HMAC keyed with the secret, so the comparison neither stops at the
+/// first differing byte nor depends on the length of the token.
+fn verify_token(secret: &str, token: &str) -> Result<()> {
+    let mac = |value: &str| -> Result<HmacSha256> {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+            .map_err(|e| anyhow!("Invalid webhook secret: {}", e))?;
+        mac.update(value.as_bytes());
+        Ok(mac)
+    };
+    
+    let expected = mac(secret)?.finalize().into_bytes();
+    mac(token)?.verify_slice(&expected)
+        .map_err(|_| anyhow!("Token does not match"))
+}
+
+/// Verify a GitHub `sha256=<hex>` HMAC signature of the request body
+fn verify_signature(body: &[u8], secret: &str, signature: &str) -> Result<()> {
+    let signature = signature.strip_prefix("sha256=")
+        .ok_or_else(|| anyhow!("Unsupported signature format"))?;
+    let expected = hex::decode(signature)
+        .context("Signature is not valid hex")?;
+    
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .map_err(|e| anyhow!("Invalid webhook secret: {}", e))?;
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| anyhow!("Signature does not match"))
+}
+
+/// Parse the repository and branches a provider's push event refers to
+fn parse_event(provider: WebhookProvider, headers: &HeaderMap, payload: &serde_json::Value) -> Result<WebhookEvent> {
+    let (event, repo_pointers): (&str, &[&str]) = match provider {
+        WebhookProvider::Github | WebhookProvider::Gitea => {
+            let header = if provider == WebhookProvider::Gitea { "x-gitea-event" } else { "x-github-event" };
+            let event = header_value(headers, header).unwrap_or("push");
+            (event, &["/repository/clone_url", "/repository/ssh_url", "/repository/git_url", "/repository/html_url"])
+        }
+        WebhookProvider::Gitlab => {
+            let event = payload.get("object_kind").and_then(|v| v.as_str()).unwrap_or("push");
+            (event, &["/project/git_http_url", "/project/git_ssh_url", "/project/web_url",
+                      "/repository/git_http_url", "/repository/git_ssh_url", "/repository/homepage"])
+        }
+        WebhookProvider::Bitbucket => {
+            let event = header_value(headers, "x-event-key").unwrap_or("repo:push");
+            (event, &["/repository/links/html/href"])
+        }
+    };
+    
+    match event {
+        "ping" | "diagnostics:ping" => return Ok(WebhookEvent::Ping),
+        // Bitbucket Cloud and Bitbucket Server name their push events differently
+        "push" | "repo:push" | "repo:refs_changed" => {}
+        other => return Ok(WebhookEvent::Ignored(other.to_string())),
+    }
+    
+    let mut repo_urls: Vec<&str> = repo_pointers.iter()
+        .filter_map(|pointer| payload.pointer(pointer).and_then(|v| v.as_str()))
+        .collect();
+    
+    // Bitbucket Server lists its clone URLs
+    if let Some(links) = payload.pointer("/repository/links/clone").and_then(|v| v.as_array()) {
+        repo_urls.extend(links.iter().filter_map(|link| link.get("href").and_then(|v| v.as_str())));
+    }
+    
+    if repo_urls.is_empty() {
+        return Err(anyhow!("Push payload does not contain a repository URL"));
+    }
+    
+    let refs: Vec<&str> = match provider {
+        WebhookProvider::Bitbucket => {
+            // Cloud: push.changes[].new {type, name}; Server: changes[].refId
+            let cloud = payload.pointer("/push/changes").and_then(|v| v.as_array()).into_iter().flatten()
+                .filter(|change| change.pointer("/new/type").and_then(|v| v.as_str()) == Some("branch"))
+                .filter_map(|change| change.pointer("/new/name").and_then(|v| v.as_str()));
+            let server = payload.get("changes").and_then(|v| v.as_array()).into_iter().flatten()
+                .filter_map(|change| change.get("refId").and_then(|v| v.as_str()))
+                .filter_map(|r| r.strip_prefix("refs/heads/"));
+            cloud.chain(server).collect()
+        }
+        _ => payload.get("ref").and_then(|v| v.as_str())
+            .and_then(|r| r.strip_prefix("refs/heads/"))
+            .into_iter()
+            .collect(),
+    };
+    
+    if refs.is_empty() {
+        return Ok(WebhookEvent::Ignored("push without branch changes".to_string()));
+    }
+    
+    Ok(WebhookEvent::Push {
+        repo_urls: repo_urls.into_iter().map(normalize_repo_url).collect(),
+        branches: refs.into_iter().map(String::from).collect(),
+    })
+}
+
+/// Wake the monitoring tasks of services watching one of the pushed branches of any of their repositories
+fn trigger_matching_services(repo_urls: &[String], branches: &[String]) -> Vec<String> {
+    let triggers = match TRIGGERS.lock() {
+        Ok(triggers) => triggers,
+        Err(_) => return vec![],
+    };
+    
+    let mut triggered: Vec<String> = triggers.iter()
+        .filter(|(_, trigger)| trigger.repos.iter()
+            .any(|(repo_url, branch)| repo_urls.contains(repo_url) && branches.contains(branch)))
+        .map(|(name, trigger)| {
+            trigger.notify.notify_one();
+            name.clone()
+        })
+        .collect();
+    triggered.sort();
+    
+    triggered
+}
+
+/// Reduce a repository URL to `host/owner/repo` so HTTPS and SSH forms compare equal
+fn normalize_repo_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    
+    let without_scheme = match url.find("://") {
+        Some(idx) => url[idx + 3..].to_string(),
+        // scp-like syntax: git@host:owner/repo
+        None => url.replacen(':', "/", 1),
+    };
+    
+    // Drop any credentials in front of the host
+    let without_user = match without_scheme.find('@') {
+        Some(idx) => &without_scheme[idx + 1..],
+        None => &without_scheme,
+    };
+    
+    without_user.to_lowercase()
+}
+
+/// Get a header value as a string
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_normalize_repo_url() {
+        let expected = "github.com/nuniesmith/watcher";
+        assert_eq!(normalize_repo_url("https://github.com/nuniesmith/watcher.git"), expected);
+        assert_eq!(normalize_repo_url("git@github.com:nuniesmith/watcher.git"), expected);
+        assert_eq!(normalize_repo_url("ssh://git@github.com/nuniesmith/watcher"), expected);
+        assert_eq!(normalize_repo_url("https://token@GitHub.com/nuniesmith/watcher/"), expected);
+    }
+    
+    #[test]
+    fn test_verify_signature() {
+        let body = b"{\"ref\":\"refs/heads/main\"}";
+        let mut mac = HmacSha256::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        
+        assert!(verify_signature(body, "secret", &signature).is_ok());
+        assert!(verify_signature(body, "other", &signature).is_err());
+        assert!(verify_signature(b"tampered", "secret", &signature).is_err());
+    }
+    
+    #[test]
+    fn test_verify_token() {
+        assert!(verify_token("secret", "secret").is_ok());
+        assert!(verify_token("secret", "secreT").is_err());
+        assert!(verify_token("secret", "secret-and-more").is_err());
+        assert!(verify_token("secret", "").is_err());
+        assert!(verify_request(WebhookProvider::Gitlab, b"{}", "secret", "secret").is_ok());
+        assert!(verify_request(WebhookProvider::Gitlab, b"{}", "secret", "wrong").is_err());
+    }
+    
+    #[test]
+    fn test_parse_bitbucket_push() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-event-key", "repo:push".parse().unwrap());
+        let payload = serde_json::json!({
+            "repository": {"links": {"html": {"href": "https://bitbucket.org/team/configs"}}},
+            "push": {"changes": [
+                {"new": {"type": "branch", "name": "main"}},
+                {"new": {"type": "tag", "name": "v1.0"}}
+            ]}
+        });
+        
+        assert_eq!(detect_provider(&headers), Some(WebhookProvider::Bitbucket));
+        assert_eq!(parse_event(WebhookProvider::Bitbucket, &headers, &payload).unwrap(), WebhookEvent::Push {
+            repo_urls: vec!["bitbucket.org/team/configs".to_string()],
+            branches: vec!["main".to_string()],
+        });
+        
+        // Payloads without a repository are rejected rather than matched against everything
+        assert!(parse_event(WebhookProvider::Bitbucket, &headers, &serde_json::json!({})).is_err());
+    }
+}
\ No newline at end of file